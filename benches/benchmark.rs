@@ -1,32 +1,45 @@
 use criterion::{Criterion, criterion_group, criterion_main};
 use microfetch_lib::{
+  Fields,
+  Root,
   UtsName,
   desktop::get_desktop_info,
   dots::print_dots,
+  field::REGISTRY,
   release::{get_os_pretty_name, get_system_info},
-  system::{
-    get_memory_usage,
-    get_root_disk_usage,
-    get_shell,
-    get_username_and_hostname,
-  },
+  render,
+  system::{get_memory_info, get_root_disk_usage, get_shell, get_user_info},
   uptime::get_current,
 };
 
 fn main_benchmark(c: &mut Criterion) {
   let utsname = UtsName::uname().expect("Failed to get uname");
-  c.bench_function("user_info", |b| {
-    b.iter(|| get_username_and_hostname(&utsname));
-  });
-  c.bench_function("os_name", |b| b.iter(get_os_pretty_name));
+  let root = Root::default();
+  c.bench_function("user_info", |b| b.iter(|| get_user_info(Some(&utsname))));
+  c.bench_function("os_name", |b| b.iter(|| get_os_pretty_name(&root)));
   c.bench_function("kernel_version", |b| b.iter(|| get_system_info(&utsname)));
   c.bench_function("shell", |b| b.iter(get_shell));
 
   c.bench_function("desktop", |b| b.iter(get_desktop_info));
-  c.bench_function("uptime", |b| b.iter(get_current));
-  c.bench_function("memory_usage", |b| b.iter(get_memory_usage));
-  c.bench_function("storage", |b| b.iter(get_root_disk_usage));
+  c.bench_function("uptime", |b| b.iter(|| get_current(&root)));
+  c.bench_function("memory_usage", |b| b.iter(|| get_memory_info(&root)));
+  c.bench_function("storage", |b| b.iter(|| get_root_disk_usage(&root)));
   c.bench_function("colors", |b| b.iter(print_dots));
+
+  c.bench_function("collect", |b| {
+    b.iter(|| Fields::collect(REGISTRY, root.clone()));
+  });
+
+  let memory = get_memory_info(&root).expect("Failed to get memory info");
+  let storage = get_root_disk_usage(&root).expect("Failed to get disk usage");
+  let uptime = get_current(&root).expect("Failed to get uptime");
+  c.bench_function("render_memory", |b| {
+    b.iter(|| render::memory(&memory, None));
+  });
+  c.bench_function("render_storage", |b| {
+    b.iter(|| render::disk_usage(&storage, None));
+  });
+  c.bench_function("render_uptime", |b| b.iter(|| render::uptime(&uptime)));
 }
 
 criterion_group!(benches, main_benchmark);
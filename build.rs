@@ -0,0 +1,298 @@
+//! Bakes an optional configuration file into the binary.
+//!
+//! Point `MICROFETCH_CONFIG` at a file in the same format as the runtime
+//! configuration file to have field selection, labels, icons and colors
+//! compiled into `const` items. Two extra keys are only understood here:
+//!
+//! - `logo = path/to/logo.txt` embeds a logo, with `${cN}` color placeholders.
+//!   Relative paths are resolved against the configuration file.
+//! - `palette.<color> = <SGR parameters>`, e.g. `palette.blue = 1;34`, replaces
+//!   the escape sequence of one of the 16 named colors.
+//!
+//! A binary built this way never looks for a runtime configuration file.
+
+use std::{
+  env,
+  fmt::{self, Write as _},
+  fs,
+  path::{Path, PathBuf},
+};
+
+use syntax::{
+  Color,
+  FIELDS,
+  PALETTE,
+  Property,
+  Role,
+  Setting,
+  Thresholds,
+  UsageDisplay,
+};
+
+// The configuration format is shared with the runtime parser
+#[allow(dead_code)]
+#[path = "src/config/syntax.rs"]
+mod syntax;
+#[allow(dead_code)]
+#[path = "src/width.rs"]
+mod width;
+
+#[derive(Default)]
+struct Style {
+  label:       Option<String>,
+  icon:        Option<String>,
+  icon_color:  Option<Color>,
+  label_color: Option<Color>,
+  thresholds:  Option<Thresholds>,
+  usage:       Option<UsageDisplay>,
+  bar_width:   Option<u8>,
+  bar_chars:   Option<[char; 2]>,
+}
+
+impl Style {
+  fn apply(&mut self, property: Property) {
+    match property {
+      Property::Label(label) => self.label = Some(label.to_owned()),
+      Property::Icon(icon) => self.icon = Some(icon.to_owned()),
+      Property::IconColor(color) => self.icon_color = Some(color),
+      Property::LabelColor(color) => self.label_color = Some(color),
+      Property::Thresholds(thresholds) => self.thresholds = Some(thresholds),
+      Property::Usage(usage) => self.usage = Some(usage),
+      Property::BarWidth(width) => self.bar_width = Some(width),
+      Property::BarChars(chars) => self.bar_chars = Some(chars),
+    }
+  }
+}
+
+#[derive(Default)]
+struct Baked {
+  fields:  Option<Vec<String>>,
+  styles:  Vec<(String, Style)>,
+  logo:    Option<Vec<String>>,
+  palette: Vec<(String, String)>,
+  theme:   Option<String>,
+  roles:   Vec<(Role, Color)>,
+}
+
+/// Whether the field called `name` is compiled in, going by the enabled cargo
+/// features.
+fn compiled_in(name: &str) -> bool {
+  FIELDS.iter().any(|&(field, feature)| {
+    field == name
+      && feature.is_none_or(|feature| {
+        let var = format!("CARGO_FEATURE_{}", feature.to_uppercase());
+        env::var_os(var).is_some()
+      })
+  })
+}
+
+fn parse(path: &Path) -> Baked {
+  let source = fs::read_to_string(path)
+    .unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+  let mut baked = Baked::default();
+
+  for (index, line) in source.lines().enumerate() {
+    let line_no = index + 1;
+    let setting = syntax::parse_line(line)
+      .unwrap_or_else(|err| panic!("{}:{line_no}: {err}", path.display()));
+
+    // Fields left out of this build are skipped, like at runtime
+    let skip = |name: &str| {
+      let skip = !compiled_in(name);
+      if skip {
+        println!(
+          "cargo:warning={}:{line_no}: field '{name}' is not compiled in, \
+           ignoring it",
+          path.display()
+        );
+      }
+      skip
+    };
+
+    match setting {
+      None => {},
+      Some(Setting::Fields(names)) => {
+        baked.fields = Some(
+          names
+            .into_iter()
+            .filter(|name| !skip(name))
+            .map(str::to_owned)
+            .collect(),
+        );
+      },
+      Some(Setting::Theme(name)) => baked.theme = Some(name.to_owned()),
+      Some(Setting::Role(role, color)) => baked.roles.push((role, color)),
+      Some(Setting::Style(name, property)) => {
+        if skip(name) {
+          continue;
+        }
+        let style = if let Some(i) =
+          baked.styles.iter().position(|(field, _)| field == name)
+        {
+          &mut baked.styles[i].1
+        } else {
+          baked.styles.push((name.to_owned(), Style::default()));
+          &mut baked.styles.last_mut().unwrap().1
+        };
+        style.apply(property);
+      },
+      Some(Setting::Logo(logo)) => {
+        let logo_path =
+          path.parent().unwrap_or_else(|| Path::new(".")).join(logo);
+        println!("cargo:rerun-if-changed={}", logo_path.display());
+        let art = fs::read_to_string(&logo_path)
+          .unwrap_or_else(|err| panic!("{}: {err}", logo_path.display()));
+        baked.logo = Some(
+          art
+            .trim_end()
+            .lines()
+            .map(|line| line.trim_end().to_owned())
+            .collect(),
+        );
+      },
+      Some(Setting::Palette(color, params)) => {
+        baked.palette.push((color.to_owned(), params.to_owned()));
+      },
+    }
+  }
+
+  baked
+}
+
+fn option_str(value: Option<&str>) -> String {
+  value.map_or_else(
+    || "None".to_owned(),
+    |s| format!("Some(::std::borrow::Cow::Borrowed({s:?}))"),
+  )
+}
+
+/// `Some` of the item at `path` that `value` is written as by `Debug`, e.g.
+/// `Some(crate::colors::Color::Red)`, or `None`.
+fn option_code(path: &str, value: Option<impl fmt::Debug>) -> String {
+  value.map_or_else(
+    || "None".to_owned(),
+    |value| format!("Some({path}::{value:?})"),
+  )
+}
+
+/// Generates `config.rs`, included by the `config::baked` module.
+fn generate_config(baked: Option<&Baked>) -> String {
+  let mut out = String::new();
+  let baked_default = Baked::default();
+  let enabled = baked.is_some();
+  let baked = baked.unwrap_or(&baked_default);
+
+  writeln!(out, "pub const ENABLED: bool = {enabled};").unwrap();
+
+  match &baked.fields {
+    Some(fields) => {
+      writeln!(
+        out,
+        "pub const FIELDS: Option<&[&str]> = Some(&{fields:?});"
+      )
+      .unwrap();
+    },
+    None => writeln!(out, "pub const FIELDS: Option<&[&str]> = None;").unwrap(),
+  }
+
+  writeln!(out, "pub const STYLES: &[crate::config::FieldStyle] = &[").unwrap();
+  for (name, style) in &baked.styles {
+    writeln!(
+      out,
+      "  crate::config::FieldStyle {{ name: {name:?}, label: {}, icon: {}, \
+       icon_color: {}, label_color: {}, thresholds: {}, usage: {}, bar_width: \
+       {:?}, bar_chars: {:?} }},",
+      option_str(style.label.as_deref()),
+      option_str(style.icon.as_deref()),
+      option_code("crate::colors::Color", style.icon_color),
+      option_code("crate::colors::Color", style.label_color),
+      option_code("crate::config::Thresholds", style.thresholds),
+      option_code("crate::config::UsageDisplay", style.usage),
+      style.bar_width,
+      style.bar_chars,
+    )
+    .unwrap();
+  }
+  writeln!(out, "];").unwrap();
+
+  let theme = baked
+    .theme
+    .as_ref()
+    .map_or_else(|| "None".to_owned(), |theme| format!("Some({theme:?})"));
+  writeln!(out, "pub const THEME: Option<&str> = {theme};").unwrap();
+  writeln!(
+    out,
+    "pub const ROLES: &[(crate::theme::Role, crate::colors::Color)] = &[",
+  )
+  .unwrap();
+  for (role, color) in &baked.roles {
+    writeln!(
+      out,
+      "  (crate::theme::Role::{role:?}, crate::colors::Color::{color:?}),"
+    )
+    .unwrap();
+  }
+  writeln!(out, "];").unwrap();
+
+  match &baked.logo {
+    Some(lines) => {
+      writeln!(
+        out,
+        "pub const LOGO: Option<crate::logo::Logo> = Some(crate::logo::Logo \
+         {{ name: \"custom\", lines: &{lines:?}, small: None }});",
+      )
+      .unwrap();
+    },
+    None => {
+      writeln!(out, "pub const LOGO: Option<crate::logo::Logo> = None;")
+        .unwrap();
+    },
+  }
+
+  out
+}
+
+/// Generates `palette.rs`, included by `colors.rs`: a macro for every color,
+/// expanding to its escape sequence as a string literal.
+fn generate_palette(overrides: &[(String, String)]) -> String {
+  let mut out = String::new();
+
+  for &(name, default) in PALETTE {
+    let params = overrides
+      .iter()
+      .rev()
+      .find(|(color, _)| color == name)
+      .map_or(default, |(_, params)| params.as_str());
+    writeln!(out, "#[macro_export]").unwrap();
+    writeln!(
+      out,
+      "macro_rules! {} {{() => {{\"\\x1b[{params}m\"}}}}",
+      name.to_uppercase()
+    )
+    .unwrap();
+  }
+
+  out
+}
+
+fn main() {
+  println!("cargo:rerun-if-changed=build.rs");
+  println!("cargo:rerun-if-changed=src/config/syntax.rs");
+  println!("cargo:rerun-if-changed=src/width.rs");
+  println!("cargo:rerun-if-env-changed=MICROFETCH_CONFIG");
+
+  let baked = env::var_os("MICROFETCH_CONFIG").map(|path| {
+    let path = PathBuf::from(path);
+    println!("cargo:rerun-if-changed={}", path.display());
+    parse(&path)
+  });
+
+  let out_dir = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+  fs::write(out_dir.join("config.rs"), generate_config(baked.as_ref()))
+    .unwrap();
+  fs::write(
+    out_dir.join("palette.rs"),
+    generate_palette(baked.as_ref().map_or(&[], |b| &b.palette)),
+  )
+  .unwrap();
+}
@@ -1,5 +1,5 @@
 {
-  description = "A microscopic fetch script in Rust, for NixOS systems";
+  description = "A microscopic fetch script in Rust, for Linux systems";
   inputs.nixpkgs.url = "github:NixOS/nixpkgs?ref=nixos-unstable";
 
   outputs = {
@@ -28,13 +28,13 @@ in
           (fs.fileFilter (file: builtins.any file.hasExt ["rs"]) (s + /src))
           (s + /Cargo.lock)
           (s + /Cargo.toml)
+          (s + /build.rs)
           (s + /benches)
         ];
       };
 
     cargoLock.lockFile = ../Cargo.lock;
     enableParallelBuilding = true;
-    buildNoDefaultFeatures = true;
     doCheck = false;
 
     # Only set RUSTFLAGS for mold if useMold is enabled
@@ -44,7 +44,7 @@ in
     };
 
     meta = {
-      description = "Microscopic fetch script in Rust, for NixOS systems";
+      description = "Microscopic fetch script in Rust, for Linux systems";
       homepage = "https://github.com/NotAShelf/microfetch";
       license = lib.licenses.gpl3Only;
       maintainers = [lib.maintainers.NotAShelf];
@@ -0,0 +1,515 @@
+//! A small, dependency-free command-line parser.
+
+use std::{error::Error, fmt};
+
+use crate::{
+  Root,
+  colors::{Background, ColorChoice},
+  field::{self, Field, REGISTRY},
+  graphics::Protocol,
+  logo::{self, Logo},
+  render::Layout,
+  theme::{self, Theme},
+};
+
+pub const HELP: &str = "\
+Microscopic fetch tool in Rust, for Linux systems
+
+Usage: microfetch [OPTIONS]
+
+Options:
+      --color <WHEN>       When to use colors: auto, always or never
+      --theme <NAME>       Color the fetch with the named theme
+      --background <KIND>  Terminal background: dark, light or detect
+      --logo <NAME>        Draw the named logo instead of the detected one
+      --logo-file <FILE>   Draw the logo in FILE, with ${c1} to ${c6} colors
+      --image <FILE>       Draw the QOI or PPM image in FILE as the logo
+      --graphics <NAME>    Image protocol: auto, kitty or sixel
+      --no-logo            Do not draw the logo
+      --layout <NAME>      Place the logo left, above or right, or none
+      --small              Draw the small variant of the logo
+      --only <FIELDS>      Comma-separated list of fields to show, in order
+      --exclude <FIELDS>   Comma-separated list of fields to hide
+      --format <NAME>      Output format: pretty, json or shell
+      --root <DIR>         Read system files relative to DIR instead of /
+      --save <FILE>        Also save the collected fields as a snapshot
+      --load <FILE>        Render a saved snapshot instead of this system
+      --mock               Render fixed, made-up values
+      --json               Shorthand for --format json
+      --shell              Shorthand for --format shell
+      --schema             Print the JSON Schema of the json format
+      --errors             Report fields that could not be collected
+  -h, --help               Print help
+  -V, --version            Print version
+";
+
+/// The full help text, listing the fields compiled into this binary.
+#[must_use]
+pub fn help() -> String {
+  let mut help = HELP.to_owned();
+  help.push_str("\nFields:");
+  for (i, field) in REGISTRY.iter().enumerate() {
+    help.push_str(if i == 0 { " " } else { ", " });
+    help.push_str(field.name());
+  }
+  help.push_str("\nLogos:");
+  for (i, logo) in logo::REGISTRY.iter().enumerate() {
+    help.push_str(if i == 0 { " " } else { ", " });
+    help.push_str(logo.name);
+  }
+  help.push_str("\nThemes:");
+  for (i, theme) in theme::THEMES.iter().enumerate() {
+    help.push_str(if i == 0 { " " } else { ", " });
+    help.push_str(theme.name);
+  }
+  help.push('\n');
+  help
+}
+
+/// How the collected fields are written out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+  /// The logo and colored, labelled rows.
+  #[default]
+  Pretty,
+  /// A single JSON object, see [`crate::json`].
+  Json,
+  /// Shell variable assignments, see [`crate::shell`].
+  Shell,
+}
+
+impl Format {
+  #[must_use]
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "pretty" => Some(Self::Pretty),
+      "json" => Some(Self::Json),
+      "shell" => Some(Self::Shell),
+      _ => None,
+    }
+  }
+}
+
+/// Where the displayed fields come from.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Source {
+  /// Collected from the system at `--root`.
+  #[default]
+  System,
+  /// Loaded from a snapshot file, see [`crate::snapshot`].
+  Snapshot(String),
+  /// The built-in [`crate::snapshot::MOCK`] snapshot.
+  Mock,
+}
+
+/// Which logo to draw.
+#[derive(Clone, Default)]
+pub enum LogoChoice {
+  /// The logo of the described system, see [`logo::for_release`].
+  #[default]
+  Auto,
+  /// No logo at all.
+  Hidden,
+  Named(&'static Logo),
+  /// A logo file, see [`logo::load`].
+  File(String),
+  /// An image file, see [`crate::image::load`].
+  Image(String),
+}
+
+/// Options for a regular fetch.
+pub struct Args {
+  pub color:      ColorChoice,
+  /// Theme selected with `--theme`, replacing the configured one.
+  pub theme:      Option<&'static Theme>,
+  /// Background to pick colors for, asked from the terminal if `None`.
+  pub background: Option<Background>,
+  pub logo:       LogoChoice,
+  pub layout:     Layout,
+  pub small:      bool,
+  /// Protocol to draw `--image` with, detected if `None`.
+  pub graphics:   Option<Protocol>,
+  /// Fields selected with `--only`, in order.
+  pub only:       Option<Vec<&'static dyn Field>>,
+  /// Fields hidden with `--exclude`.
+  pub exclude:    Vec<&'static dyn Field>,
+  pub format:     Format,
+  pub errors:     bool,
+  /// The system to describe, see [`Root`].
+  pub root:       Root,
+  pub source:     Source,
+  /// Snapshot file to save the collected fields to.
+  pub save:       Option<String>,
+}
+
+impl Default for Args {
+  fn default() -> Self {
+    Self {
+      color:      ColorChoice::Auto,
+      theme:      None,
+      background: Some(Background::Dark),
+      logo:       LogoChoice::Auto,
+      layout:     Layout::Left,
+      small:      false,
+      graphics:   None,
+      only:       None,
+      exclude:    Vec::new(),
+      format:     Format::Pretty,
+      errors:     false,
+      root:       Root::default(),
+      source:     Source::System,
+      save:       None,
+    }
+  }
+}
+
+impl Args {
+  /// Resolves the fields to display. `--only` takes precedence over
+  /// `default`, which in turn falls back to every registered field, and
+  /// `--exclude` is applied last.
+  #[must_use]
+  pub fn fields(
+    &self,
+    default: Option<&[&'static dyn Field]>,
+  ) -> Vec<&'static dyn Field> {
+    let mut fields = self
+      .only
+      .as_deref()
+      .or(default)
+      .unwrap_or(REGISTRY)
+      .to_vec();
+    fields.retain(|field| {
+      !self
+        .exclude
+        .iter()
+        .any(|excluded| excluded.name() == field.name())
+    });
+    fields
+  }
+}
+
+/// What the command line asked us to do.
+pub enum Command {
+  Fetch(Args),
+  Help,
+  Version,
+  Schema,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+  UnknownFlag(String),
+  MissingValue(&'static str),
+  InvalidValue { flag: &'static str, value: String },
+  UnknownField(String),
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::UnknownFlag(flag) => write!(f, "unknown option '{flag}'"),
+      Self::MissingValue(flag) => write!(f, "option '{flag}' requires a value"),
+      Self::InvalidValue { flag, value } => {
+        write!(f, "invalid value '{value}' for option '{flag}'")
+      },
+      Self::UnknownField(name) => write!(f, "unknown field '{name}'"),
+    }
+  }
+}
+
+impl Error for ParseError {}
+
+/// Resolves a comma-separated list of field names.
+fn parse_fields(list: &str) -> Result<Vec<&'static dyn Field>, ParseError> {
+  list
+    .split(',')
+    .map(str::trim)
+    .filter(|name| !name.is_empty())
+    .map(|name| {
+      field::find(name).ok_or_else(|| ParseError::UnknownField(name.to_owned()))
+    })
+    .collect()
+}
+
+/// Parses the command line, without the program name.
+///
+/// # Errors
+///
+/// Returns an error on unknown options, missing or invalid option values and
+/// unknown field names.
+pub fn parse(
+  args: impl IntoIterator<Item = String>,
+) -> Result<Command, ParseError> {
+  let mut parsed = Args::default();
+  let mut args = args.into_iter();
+
+  while let Some(arg) = args.next() {
+    // Accept both `--flag value` and `--flag=value`
+    let (flag, inline) = match arg.split_once('=') {
+      Some((flag, value)) if flag.starts_with("--") => {
+        (flag, Some(value.to_owned()))
+      },
+      _ => (arg.as_str(), None),
+    };
+
+    let mut value = |name: &'static str| {
+      inline
+        .clone()
+        .or_else(|| args.next())
+        .ok_or(ParseError::MissingValue(name))
+    };
+
+    match flag {
+      "-h" | "--help" => return Ok(Command::Help),
+      "-V" | "--version" => return Ok(Command::Version),
+      "--schema" => return Ok(Command::Schema),
+      "--color" => {
+        let when = value("--color")?;
+        parsed.color =
+          ColorChoice::from_name(&when).ok_or(ParseError::InvalidValue {
+            flag:  "--color",
+            value: when,
+          })?;
+      },
+      "--theme" => {
+        let name = value("--theme")?;
+        parsed.theme =
+          Some(theme::find(&name).ok_or(ParseError::InvalidValue {
+            flag:  "--theme",
+            value: name,
+          })?);
+      },
+      "--background" => {
+        let name = value("--background")?;
+        parsed.background = match name.as_str() {
+          "detect" => None,
+          _ => {
+            Some(Background::from_name(&name).ok_or(
+              ParseError::InvalidValue {
+                flag:  "--background",
+                value: name,
+              },
+            )?)
+          },
+        };
+      },
+      "--format" => {
+        let name = value("--format")?;
+        parsed.format =
+          Format::from_name(&name).ok_or(ParseError::InvalidValue {
+            flag:  "--format",
+            value: name,
+          })?;
+      },
+      "--root" => parsed.root = Root::new(&value("--root")?),
+      "--load" => parsed.source = Source::Snapshot(value("--load")?),
+      "--save" => parsed.save = Some(value("--save")?),
+      "--only" => parsed.only = Some(parse_fields(&value("--only")?)?),
+      "--exclude" => {
+        parsed.exclude.extend(parse_fields(&value("--exclude")?)?);
+      },
+      "--logo" => {
+        let name = value("--logo")?;
+        parsed.logo = LogoChoice::Named(logo::find(&name).ok_or(
+          ParseError::InvalidValue {
+            flag:  "--logo",
+            value: name,
+          },
+        )?);
+      },
+      "--layout" => {
+        let name = value("--layout")?;
+        parsed.layout =
+          Layout::from_name(&name).ok_or(ParseError::InvalidValue {
+            flag:  "--layout",
+            value: name,
+          })?;
+      },
+      "--image" => parsed.logo = LogoChoice::Image(value("--image")?),
+      "--graphics" => {
+        let name = value("--graphics")?;
+        parsed.graphics = match name.as_str() {
+          "auto" => None,
+          _ => {
+            Some(Protocol::from_name(&name).ok_or(
+              ParseError::InvalidValue {
+                flag:  "--graphics",
+                value: name,
+              },
+            )?)
+          },
+        };
+      },
+      "--logo-file" => parsed.logo = LogoChoice::File(value("--logo-file")?),
+      "--no-logo" if inline.is_none() => parsed.logo = LogoChoice::Hidden,
+      "--small" if inline.is_none() => parsed.small = true,
+      "--errors" if inline.is_none() => parsed.errors = true,
+      "--mock" if inline.is_none() => parsed.source = Source::Mock,
+      "--json" if inline.is_none() => parsed.format = Format::Json,
+      "--shell" if inline.is_none() => parsed.format = Format::Shell,
+      _ => return Err(ParseError::UnknownFlag(arg)),
+    }
+  }
+
+  Ok(Command::Fetch(parsed))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn parse_args(args: &[&str]) -> Result<Command, ParseError> {
+    parse(args.iter().map(|&arg| arg.to_owned()))
+  }
+
+  fn fetch(args: &[&str]) -> Args {
+    match parse_args(args) {
+      Ok(Command::Fetch(args)) => args,
+      Ok(_) => panic!("{args:?} did not ask for a fetch"),
+      Err(err) => panic!("{args:?}: {err}"),
+    }
+  }
+
+  fn error(args: &[&str]) -> ParseError {
+    match parse_args(args) {
+      Err(err) => err,
+      Ok(_) => panic!("{args:?} was accepted"),
+    }
+  }
+
+  fn names(fields: &[&'static dyn Field]) -> Vec<&'static str> {
+    fields.iter().map(|field| field.name()).collect()
+  }
+
+  #[test]
+  fn inline_and_separate_values() {
+    for args in [
+      &["--color=never", "--layout=above", "--format=json"][..],
+      &["--color", "never", "--layout", "above", "--format", "json"],
+    ] {
+      let args = fetch(args);
+      assert_eq!(args.color, ColorChoice::Never);
+      assert_eq!(args.layout, Layout::Above);
+      assert_eq!(args.format, Format::Json);
+    }
+
+    assert_eq!(
+      fetch(&["--root=/mnt"]).root,
+      fetch(&["--root", "/mnt"]).root
+    );
+    assert_eq!(
+      fetch(&["--load=a=b.json"]).source,
+      Source::Snapshot("a=b.json".to_owned())
+    );
+    assert_eq!(fetch(&["--background", "detect"]).background, None);
+    assert_eq!(
+      fetch(&["--background=light"]).background,
+      Some(Background::Light)
+    );
+    assert_eq!(fetch(&["--graphics=auto"]).graphics, None);
+    assert_eq!(
+      fetch(&["--graphics", "sixel"]).graphics,
+      Some(Protocol::Sixel)
+    );
+  }
+
+  #[test]
+  fn missing_values() {
+    assert_eq!(error(&["--color"]), ParseError::MissingValue("--color"));
+    assert_eq!(
+      error(&["--mock", "--only"]),
+      ParseError::MissingValue("--only")
+    );
+    // An empty inline value is still a value
+    assert_eq!(error(&["--format="]), ParseError::InvalidValue {
+      flag:  "--format",
+      value: String::new(),
+    });
+  }
+
+  #[test]
+  fn unknown_flags_and_values() {
+    for arg in ["--colour", "-x", "mock", "--", "--mock=", "--no-logo=x"] {
+      assert_eq!(
+        error(&[arg]),
+        ParseError::UnknownFlag(arg.to_owned()),
+        "{arg:?}"
+      );
+    }
+
+    for flag in [
+      "--color",
+      "--layout",
+      "--background",
+      "--format",
+      "--graphics",
+      "--theme",
+      "--logo",
+    ] {
+      assert_eq!(error(&[flag, "bogus"]), ParseError::InvalidValue {
+        flag,
+        value: "bogus".to_owned(),
+      });
+    }
+  }
+
+  #[test]
+  fn format_shorthands() {
+    assert_eq!(fetch(&[]).format, Format::Pretty);
+    assert_eq!(fetch(&["--json"]).format, Format::Json);
+    assert_eq!(fetch(&["--shell"]).format, Format::Shell);
+    // The last of them wins
+    assert_eq!(
+      fetch(&["--json", "--format", "shell"]).format,
+      Format::Shell
+    );
+    assert_eq!(fetch(&["--format", "shell", "--json"]).format, Format::Json);
+    assert_eq!(
+      fetch(&["--shell", "--format=pretty"]).format,
+      Format::Pretty
+    );
+    assert_eq!(
+      error(&["--json=yes"]),
+      ParseError::UnknownFlag("--json=yes".to_owned())
+    );
+  }
+
+  #[test]
+  fn field_lists() {
+    let args = fetch(&["--only", "kernel, os,,user"]);
+    assert_eq!(names(&args.fields(None)), ["kernel", "os", "user"]);
+
+    // A later --only replaces an earlier one, --exclude accumulates
+    let args = fetch(&[
+      "--only=user",
+      "--only=os,kernel,user",
+      "--exclude",
+      "user",
+      "--exclude=os",
+    ]);
+    assert_eq!(names(&args.fields(None)), ["kernel"]);
+
+    // --only takes precedence over the configured fields
+    let configured = [field::find("os").unwrap()];
+    assert_eq!(names(&fetch(&[]).fields(Some(&configured))), ["os"]);
+    let args = fetch(&["--only=kernel"]);
+    assert_eq!(names(&args.fields(Some(&configured))), ["kernel"]);
+
+    assert_eq!(
+      error(&["--exclude", "os,gpu"]),
+      ParseError::UnknownField("gpu".to_owned())
+    );
+  }
+
+  #[test]
+  fn commands() {
+    assert!(matches!(parse_args(&["--mock", "-h"]), Ok(Command::Help)));
+    assert!(matches!(parse_args(&["--version"]), Ok(Command::Version)));
+    assert!(matches!(parse_args(&["--schema"]), Ok(Command::Schema)));
+    // Parsing stops at the first command
+    assert!(matches!(
+      parse_args(&["-V", "--bogus"]),
+      Ok(Command::Version)
+    ));
+  }
+}
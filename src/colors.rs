@@ -1,61 +1,517 @@
-use std::sync::LazyLock;
+use std::{
+  borrow::Cow,
+  sync::{
+    LazyLock,
+    atomic::{AtomicU8, Ordering},
+  },
+};
+
+pub use crate::config::syntax::Color;
+use crate::{getenv, terminfo::Terminfo};
 
 // All this because concat!() doesn't accept const parameters
 // See https://github.com/rust-lang/rust/issues/31383
-#[macro_export]
-macro_rules! RESET    {() => {"\x1b[0m"}}
-#[macro_export]
-macro_rules! BLUE     {() => {"\x1b[34m"}}
-#[macro_export]
-macro_rules! CYAN     {() => {"\x1b[34m"}}
-#[macro_export]
-macro_rules! GREEN    {() => {"\x1b[32m"}}
-#[macro_export]
-macro_rules! YELLOW   {() => {"\x1b[33m"}}
-#[macro_export]
-macro_rules! RED      {() => {"\x1b[31m"}}
-#[macro_export]
-macro_rules! MAGENTA  {() => {"\x1b[35m"}}
+//
+// The macros are written out by build.rs, so that the palette can be
+// overridden at compile time with MICROFETCH_CONFIG. Each one expands to a
+// string literal, e.g. `RESET!()` to "\x1b[0m".
+include!(concat!(env!("OUT_DIR"), "/palette.rs"));
 
+/// The escape sequences of the terminal's 16 named colors, which are what the
+/// terminal's own color scheme applies to. Any other [`Color`] is written out
+/// with [`Colors::paint`], in as many colors as the terminal supports.
 pub struct Colors {
-  pub reset:   &'static str,
-  pub blue:    &'static str,
-  pub cyan:    &'static str,
-  pub green:   &'static str,
-  pub yellow:  &'static str,
-  pub red:     &'static str,
+  pub reset:          &'static str,
+  pub black:          &'static str,
+  pub red:            &'static str,
+  pub green:          &'static str,
+  pub yellow:         &'static str,
+  pub blue:           &'static str,
+  pub magenta:        &'static str,
+  pub cyan:           &'static str,
+  pub white:          &'static str,
+  pub bright_black:   &'static str,
+  pub bright_red:     &'static str,
+  pub bright_green:   &'static str,
+  pub bright_yellow:  &'static str,
+  pub bright_blue:    &'static str,
+  pub bright_magenta: &'static str,
+  pub bright_cyan:    &'static str,
+  pub bright_white:   &'static str,
+  enabled:            bool,
 }
 
 impl Colors {
   const fn new(is_no_color: bool) -> Self {
     if is_no_color {
       Self {
-        reset:   "",
-        blue:    "",
-        cyan:    "",
-        green:   "",
-        yellow:  "",
-        red:     "",
+        reset:          "",
+        black:          "",
+        red:            "",
+        green:          "",
+        yellow:         "",
+        blue:           "",
+        magenta:        "",
+        cyan:           "",
+        white:          "",
+        bright_black:   "",
+        bright_red:     "",
+        bright_green:   "",
+        bright_yellow:  "",
+        bright_blue:    "",
+        bright_magenta: "",
+        bright_cyan:    "",
+        bright_white:   "",
+        enabled:        false,
       }
     } else {
       Self {
-        reset:   RESET!(),
-        blue:    BLUE!(),
-        cyan:    CYAN!(),
-        green:   GREEN!(),
-        yellow:  YELLOW!(),
-        red:     RED!(),
+        reset:          RESET!(),
+        black:          BLACK!(),
+        red:            RED!(),
+        green:          GREEN!(),
+        yellow:         YELLOW!(),
+        blue:           BLUE!(),
+        magenta:        MAGENTA!(),
+        cyan:           CYAN!(),
+        white:          WHITE!(),
+        bright_black:   BRIGHT_BLACK!(),
+        bright_red:     BRIGHT_RED!(),
+        bright_green:   BRIGHT_GREEN!(),
+        bright_yellow:  BRIGHT_YELLOW!(),
+        bright_blue:    BRIGHT_BLUE!(),
+        bright_magenta: BRIGHT_MAGENTA!(),
+        bright_cyan:    BRIGHT_CYAN!(),
+        bright_white:   BRIGHT_WHITE!(),
+        enabled:        true,
       }
     }
   }
+
+  /// Swaps the colors that are hard to read on a light background for darker
+  /// ones: the bright colors for their normal variants, and yellow, green,
+  /// cyan and white for darker shades from the 256-color palette, if the
+  /// terminal has it.
+  fn for_light_background(mut self) -> Self {
+    let indexed = *DEPTH >= ColorDepth::Indexed;
+    let darker = |fixed, named| if indexed { fixed } else { named };
+    self.yellow = darker("\x1b[38;5;136m", self.yellow);
+    self.green = darker("\x1b[38;5;28m", self.green);
+    self.cyan = darker("\x1b[38;5;30m", self.cyan);
+    self.white = darker("\x1b[38;5;240m", self.bright_black);
+    self.bright_red = self.red;
+    self.bright_green = self.green;
+    self.bright_yellow = self.yellow;
+    self.bright_blue = self.blue;
+    self.bright_magenta = self.magenta;
+    self.bright_cyan = self.cyan;
+    self.bright_white = self.black;
+    self
+  }
+
+  /// The escape sequence switching to `color`, or to the nearest color the
+  /// terminal supports. Empty if colors are disabled, or for
+  /// [`Color::Default`], which keeps whatever color is current.
+  #[must_use]
+  pub fn paint(&self, color: Color) -> Cow<'static, str> {
+    // Named colors are left to the terminal, so that its depth is only looked
+    // up for the others
+    let color = match color {
+      Color::Fixed(_) | Color::Rgb(..) if self.enabled => {
+        color.downsample(*DEPTH)
+      },
+      _ => color,
+    };
+    Cow::Borrowed(match color {
+      Color::Black => self.black,
+      Color::Red => self.red,
+      Color::Green => self.green,
+      Color::Yellow => self.yellow,
+      Color::Blue => self.blue,
+      Color::Magenta => self.magenta,
+      Color::Cyan => self.cyan,
+      Color::White => self.white,
+      Color::BrightBlack => self.bright_black,
+      Color::BrightRed => self.bright_red,
+      Color::BrightGreen => self.bright_green,
+      Color::BrightYellow => self.bright_yellow,
+      Color::BrightBlue => self.bright_blue,
+      Color::BrightMagenta => self.bright_magenta,
+      Color::BrightCyan => self.bright_cyan,
+      Color::BrightWhite => self.bright_white,
+      Color::Fixed(index) if self.enabled => {
+        return Cow::Owned(format!("\x1b[38;5;{index}m"));
+      },
+      Color::Rgb(r, g, b) if self.enabled => {
+        return Cow::Owned(format!("\x1b[38;2;{r};{g};{b}m"));
+      },
+      // Either colors are disabled, or the current color is kept
+      Color::Default | Color::Fixed(_) | Color::Rgb(..) => "",
+    })
+  }
+}
+
+/// The named colors in [`Color`] order, with xterm's default values, to find
+/// the nearest one to any other color.
+const NAMED: [(Color, [u8; 3]); 16] = [
+  (Color::Black, [0, 0, 0]),
+  (Color::Red, [205, 0, 0]),
+  (Color::Green, [0, 205, 0]),
+  (Color::Yellow, [205, 205, 0]),
+  (Color::Blue, [0, 0, 238]),
+  (Color::Magenta, [205, 0, 205]),
+  (Color::Cyan, [0, 205, 205]),
+  (Color::White, [229, 229, 229]),
+  (Color::BrightBlack, [127, 127, 127]),
+  (Color::BrightRed, [255, 0, 0]),
+  (Color::BrightGreen, [0, 255, 0]),
+  (Color::BrightYellow, [255, 255, 0]),
+  (Color::BrightBlue, [92, 92, 255]),
+  (Color::BrightMagenta, [255, 0, 255]),
+  (Color::BrightCyan, [0, 255, 255]),
+  (Color::BrightWhite, [255, 255, 255]),
+];
+
+/// Channel values of the 6×6×6 color cube in the 256-color palette.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The squared distance between two colors.
+fn distance(a: [u8; 3], b: [u8; 3]) -> u32 {
+  (0..3).map(|i| u32::from(a[i].abs_diff(b[i])).pow(2)).sum()
+}
+
+/// The index of the cube level nearest to `value`.
+const fn cube_level(value: u8) -> u8 {
+  match value {
+    0..48 => 0,
+    48..115 => 1,
+    // Levels are 40 apart from here on
+    _ => (value - 35) / 40,
+  }
+}
+
+/// The channel values of an entry in the 256-color palette.
+fn fixed_rgb(index: u8) -> [u8; 3] {
+  match index {
+    0..16 => NAMED[usize::from(index)].1,
+    16..232 => {
+      let i = index - 16;
+      [i / 36, i / 6 % 6, i % 6].map(|level| CUBE[usize::from(level)])
+    },
+    _ => [8 + (index - 232) * 10; 3],
+  }
+}
+
+/// The number of colors a terminal supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+  /// The 8 colors from black to white, e.g. on the Linux console.
+  Basic,
+  /// The 8 colors and their bright variants.
+  Ansi,
+  /// The 256-color palette.
+  Indexed,
+  /// Any 24-bit color.
+  TrueColor,
+}
+
+impl ColorDepth {
+  /// The depth of a terminal supporting `colors` colors, as reported by
+  /// terminfo.
+  #[must_use]
+  pub const fn from_count(colors: u32) -> Self {
+    match colors {
+      0x0100_0000.. => Self::TrueColor,
+      256.. => Self::Indexed,
+      16.. => Self::Ansi,
+      _ => Self::Basic,
+    }
+  }
+}
+
+impl Color {
+  /// The foreground color set by SGR parameters such as `1;34` or
+  /// `38;2;126;186;228`, e.g. from an os-release `ANSI_COLOR`. Attributes
+  /// such as bold are ignored, and the last color wins. `None` if there is no
+  /// color, or the parameters are malformed.
+  #[must_use]
+  pub fn from_sgr(params: &str) -> Option<Self> {
+    let mut params = params.split(';').map(|param| param.parse::<u8>().ok());
+    let mut color = None;
+    while let Some(param) = params.next() {
+      color = match param? {
+        code @ 30..=37 => Some(NAMED[usize::from(code - 30)].0),
+        code @ 90..=97 => Some(NAMED[usize::from(code - 90 + 8)].0),
+        // Extended colors, for the foreground or the background
+        code @ (38 | 48) => {
+          let extended = match params.next()?? {
+            5 => Self::Fixed(params.next()??),
+            2 => Self::Rgb(params.next()??, params.next()??, params.next()??),
+            _ => return None,
+          };
+          if code == 38 { Some(extended) } else { color }
+        },
+        39 => None,
+        _ => color,
+      };
+    }
+    color
+  }
+
+  /// The nearest color that can be shown at `depth`. Named colors are kept
+  /// as they are, unless `depth` has no bright colors.
+  #[must_use]
+  pub fn downsample(self, depth: ColorDepth) -> Self {
+    let rgb = match self {
+      Self::Fixed(index) if depth < ColorDepth::Indexed => fixed_rgb(index),
+      Self::Rgb(r, g, b) if depth < ColorDepth::TrueColor => [r, g, b],
+      Self::BrightBlack
+      | Self::BrightRed
+      | Self::BrightGreen
+      | Self::BrightYellow
+      | Self::BrightBlue
+      | Self::BrightMagenta
+      | Self::BrightCyan
+      | Self::BrightWhite
+        if depth == ColorDepth::Basic =>
+      {
+        let index = NAMED.iter().position(|&(named, _)| named == self);
+        return index.map_or(self, |i| NAMED[i - 8].0);
+      },
+      _ => return self,
+    };
+
+    if depth == ColorDepth::Indexed {
+      let [r, g, b] = rgb.map(cube_level);
+      let cube = 16 + 36 * r + 6 * g + b;
+      // The gray ramp runs from 8 to 238 in steps of 10
+      let average =
+        (rgb.iter().map(|&c| u16::from(c)).sum::<u16>() / 3).clamp(8, 238);
+      #[allow(clippy::cast_possible_truncation)]
+      let gray = 232 + ((average - 8 + 5) / 10).min(23) as u8;
+      return [cube, gray]
+        .into_iter()
+        .min_by_key(|&index| distance(rgb, fixed_rgb(index)))
+        .map_or(self, Self::Fixed);
+    }
+
+    let named = if depth == ColorDepth::Basic {
+      &NAMED[..8]
+    } else {
+      &NAMED[..]
+    };
+    named
+      .iter()
+      .min_by_key(|&&(_, value)| distance(rgb, value))
+      .map_or(self, |&(color, _)| color)
+  }
+}
+
+/// Whether to emit colors, as requested with `--color`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum ColorChoice {
+  /// Decide based on the environment, see [`detect`].
+  #[default]
+  Auto,
+  Always,
+  Never,
+}
+
+impl ColorChoice {
+  #[must_use]
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "auto" => Some(Self::Auto),
+      "always" => Some(Self::Always),
+      "never" => Some(Self::Never),
+      _ => None,
+    }
+  }
+}
+
+static COLOR_CHOICE: AtomicU8 = AtomicU8::new(ColorChoice::Auto as u8);
+
+/// Overrides color detection. Only has an effect if called before
+/// [`IS_NO_COLOR`] or [`COLORS`] are first accessed.
+pub fn set_color_choice(choice: ColorChoice) {
+  COLOR_CHOICE.store(choice as u8, Ordering::Relaxed);
+}
+
+/// Whether colors should be used with `--color auto`, in order of
+/// precedence:
+///
+/// 1. never if `NO_COLOR` is set,
+/// 2. always if `FORCE_COLOR` or `CLICOLOR_FORCE` is set, to anything but `0`,
+/// 3. never if `TERM` is `dumb` or `CLICOLOR` is `0`, and
+/// 4. otherwise only if stdout is a terminal.
+#[must_use]
+pub fn detect() -> bool {
+  if getenv(c"NO_COLOR").is_some() {
+    return false;
+  }
+  let forced = |name| getenv(name).is_some_and(|value| value != "0");
+  if forced(c"FORCE_COLOR") || forced(c"CLICOLOR_FORCE") {
+    return true;
+  }
+  if getenv(c"TERM") == Some("dumb") || getenv(c"CLICOLOR") == Some("0") {
+    return false;
+  }
+  unsafe { libc::isatty(libc::STDOUT_FILENO) == 1 }
+}
+
+/// The brightness of the terminal background, which decides between the
+/// default palette and a darker one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum Background {
+  #[default]
+  Dark,
+  Light,
+}
+
+impl Background {
+  #[must_use]
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "dark" => Some(Self::Dark),
+      "light" => Some(Self::Light),
+      _ => None,
+    }
+  }
+
+  /// Whether a background of the color `[r, g, b]` is light or dark.
+  #[must_use]
+  pub fn from_rgb([r, g, b]: [u8; 3]) -> Self {
+    // Perceived brightness, weighing green most and blue least
+    let luma = 299 * u32::from(r) + 587 * u32::from(g) + 114 * u32::from(b);
+    if luma > 128 * 1000 {
+      Self::Light
+    } else {
+      Self::Dark
+    }
+  }
+}
+
+static BACKGROUND: AtomicU8 = AtomicU8::new(Background::Dark as u8);
+
+/// Picks the palette for the given background. Only has an effect if called
+/// before [`COLORS`] is first accessed.
+pub fn set_background(background: Background) {
+  BACKGROUND.store(background as u8, Ordering::Relaxed);
+}
+
+/// The number of colors the terminal supports:
+///
+/// 1. 24-bit if `COLORTERM` is `truecolor` or `24bit`,
+/// 2. otherwise the `colors` capability of the terminfo entry for `TERM`, and
+/// 3. the 16 named colors if there is no entry, which any color terminal has.
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn detect_depth() -> ColorDepth {
+  if matches!(getenv(c"COLORTERM"), Some("truecolor" | "24bit")) {
+    return ColorDepth::TrueColor;
+  }
+  Terminfo::from_env().map_or(ColorDepth::Ansi, |terminfo| {
+    ColorDepth::from_count(terminfo.colors().unwrap_or(0))
+  })
 }
 
 pub static IS_NO_COLOR: LazyLock<bool> = LazyLock::new(|| {
-  // Check for NO_COLOR once at startup
-  const NO_COLOR: *const libc::c_char = c"NO_COLOR".as_ptr();
-  unsafe { !libc::getenv(NO_COLOR).is_null() }
+  match COLOR_CHOICE.load(Ordering::Relaxed) {
+    x if x == ColorChoice::Always as u8 => false,
+    x if x == ColorChoice::Never as u8 => true,
+    // Checked once at startup
+    _ => !detect(),
+  }
 });
 
+/// The number of colors the terminal supports, detected the first time a
+/// color other than the named ones is painted.
+static DEPTH: LazyLock<ColorDepth> = LazyLock::new(detect_depth);
+
 pub static COLORS: LazyLock<Colors> = LazyLock::new(|| {
-  Colors::new(*IS_NO_COLOR)
+  if *IS_NO_COLOR {
+    Colors::new(true)
+  } else {
+    let colors = Colors::new(false);
+    if BACKGROUND.load(Ordering::Relaxed) == Background::Light as u8 {
+      colors.for_light_background()
+    } else {
+      colors
+    }
+  }
 });
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn from_sgr() {
+    assert_eq!(Color::from_sgr("1;34"), Some(Color::Blue));
+    assert_eq!(Color::from_sgr("0;91"), Some(Color::BrightRed));
+    assert_eq!(Color::from_sgr("38;5;202"), Some(Color::Fixed(202)));
+    assert_eq!(
+      Color::from_sgr("0;38;2;126;186;228"),
+      Some(Color::Rgb(126, 186, 228))
+    );
+    // Background colors are skipped over, not misread
+    assert_eq!(Color::from_sgr("48;5;31;32"), Some(Color::Green));
+    for params in ["", "1", "31;", "38;5", "38;2;1;2", "38;7;1", "31m"] {
+      assert_eq!(Color::from_sgr(params), None, "{params:?} was accepted");
+    }
+  }
+
+  #[test]
+  fn cube_levels() {
+    for (value, level) in [(0, 0), (47, 0), (48, 1), (114, 1), (115, 2)] {
+      assert_eq!(cube_level(value), level, "{value}");
+    }
+    for (value, level) in [(135, 2), (154, 2), (155, 3), (234, 4), (255, 5)] {
+      assert_eq!(cube_level(value), level, "{value}");
+    }
+  }
+
+  #[test]
+  fn downsamples_to_the_cube() {
+    let indexed = |r, g, b| Color::Rgb(r, g, b).downsample(ColorDepth::Indexed);
+    assert_eq!(indexed(255, 0, 0), Color::Fixed(196));
+    assert_eq!(indexed(95, 135, 175), Color::Fixed(67));
+    assert_eq!(indexed(126, 186, 228), Color::Fixed(110));
+    assert_eq!(indexed(0, 0, 0), Color::Fixed(16));
+    assert_eq!(indexed(250, 250, 250), Color::Fixed(231));
+    let fixed = Color::Fixed(67);
+    assert_eq!(fixed.downsample(ColorDepth::Indexed), fixed);
+  }
+
+  #[test]
+  fn downsamples_to_grays() {
+    let indexed = |r, g, b| Color::Rgb(r, g, b).downsample(ColorDepth::Indexed);
+    assert_eq!(indexed(8, 8, 8), Color::Fixed(232));
+    assert_eq!(indexed(40, 40, 40), Color::Fixed(235));
+    assert_eq!(indexed(128, 128, 128), Color::Fixed(244));
+    assert_eq!(indexed(130, 128, 126), Color::Fixed(244));
+  }
+
+  #[test]
+  fn downsamples_to_named_colors() {
+    let ansi = |color: Color| color.downsample(ColorDepth::Ansi);
+    assert_eq!(ansi(Color::Rgb(250, 10, 10)), Color::BrightRed);
+    assert_eq!(ansi(Color::Rgb(200, 0, 0)), Color::Red);
+    assert_eq!(ansi(Color::Rgb(120, 120, 120)), Color::BrightBlack);
+    assert_eq!(ansi(Color::Fixed(196)), Color::BrightRed);
+    assert_eq!(ansi(Color::Fixed(4)), Color::Blue);
+    assert_eq!(ansi(Color::BrightCyan), Color::BrightCyan);
+
+    // Without bright colors, even named ones are swapped
+    let basic = |color: Color| color.downsample(ColorDepth::Basic);
+    assert_eq!(basic(Color::Rgb(250, 10, 10)), Color::Red);
+    assert_eq!(basic(Color::Rgb(200, 200, 200)), Color::White);
+    assert_eq!(basic(Color::BrightCyan), Color::Cyan);
+    assert_eq!(basic(Color::Green), Color::Green);
+
+    let rgb = Color::Rgb(1, 2, 3);
+    assert_eq!(rgb.downsample(ColorDepth::TrueColor), rgb);
+  }
+}
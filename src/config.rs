@@ -0,0 +1,492 @@
+//! Optional runtime configuration, read from
+//! `$XDG_CONFIG_HOME/microfetch/config` (or `~/.config/microfetch/config`).
+//!
+//! The format is a flat list of `key = value` lines. Lines starting with `#`
+//! are comments, and values may be wrapped in double quotes to keep leading or
+//! trailing whitespace.
+//!
+//! ```text
+//! # Fields to show, in order
+//! fields = user, os, kernel, memory, storage
+//!
+//! memory.label = RAM
+//! memory.icon = "M "
+//! memory.icon_color = red
+//! memory.label_color = yellow
+//!
+//! # Usage colors: warning from 80%, critical from 95%, rather than 70 and 90
+//! memory.thresholds = 80, 95
+//! storage.thresholds = none
+//!
+//! # Usage as a bar of 20 cells after the sizes
+//! storage.usage = both
+//! storage.bar_width = 20
+//! storage.bar_chars = "#."
+//!
+//! # Colors of the parts of the fetch, see `theme`
+//! theme = nord
+//! theme.label = #d8dee9
+//! theme.accent = 208
+//! ```
+//!
+//! When no configuration file exists, the only cost is a single failed `open`.
+//! Binaries built with `MICROFETCH_CONFIG` set have their configuration baked
+//! in by `build.rs` instead, and never read a file at runtime.
+
+use std::{borrow::Cow, error::Error, fmt, io};
+
+pub mod syntax;
+
+use self::syntax::{Property, Setting};
+pub use self::syntax::{SyntaxError, Thresholds, UsageDisplay};
+use crate::{
+  colors::Color,
+  field::{self, Field},
+  getenv,
+  syscall::read_file_fast,
+  theme::{self, Role, Theme},
+};
+
+/// Presentation overrides for a single field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldStyle {
+  /// Name of the field this style applies to.
+  pub name:        &'static str,
+  pub label:       Option<Cow<'static, str>>,
+  pub icon:        Option<Cow<'static, str>>,
+  pub icon_color:  Option<Color>,
+  pub label_color: Option<Color>,
+  /// The remaining ones are only used by the fields showing a usage.
+  pub thresholds:  Option<Thresholds>,
+  pub usage:       Option<UsageDisplay>,
+  /// Number of cells in usage bars.
+  pub bar_width:   Option<u8>,
+  /// The filled and the empty cell of usage bars.
+  pub bar_chars:   Option<[char; 2]>,
+}
+
+impl FieldStyle {
+  const fn new(name: &'static str) -> Self {
+    Self {
+      name,
+      label: None,
+      icon: None,
+      icon_color: None,
+      label_color: None,
+      thresholds: None,
+      usage: None,
+      bar_width: None,
+      bar_chars: None,
+    }
+  }
+
+  fn apply(&mut self, property: Property) {
+    match property {
+      Property::Label(label) => self.label = Some(label.to_owned().into()),
+      Property::Icon(icon) => self.icon = Some(icon.to_owned().into()),
+      Property::IconColor(color) => self.icon_color = Some(color),
+      Property::LabelColor(color) => self.label_color = Some(color),
+      Property::Thresholds(thresholds) => self.thresholds = Some(thresholds),
+      Property::Usage(usage) => self.usage = Some(usage),
+      Property::BarWidth(width) => self.bar_width = Some(width),
+      Property::BarChars(chars) => self.bar_chars = Some(chars),
+    }
+  }
+}
+
+/// Configuration baked in at compile time, generated by `build.rs`.
+pub mod baked {
+  include!(concat!(env!("OUT_DIR"), "/config.rs"));
+}
+
+#[derive(Default)]
+pub struct Config {
+  /// Fields to display, in order. `None` keeps the default selection.
+  pub fields:   Option<Vec<&'static dyn Field>>,
+  pub styles:   Vec<FieldStyle>,
+  /// Theme to start from. `None` keeps the default theme.
+  pub theme:    Option<&'static Theme>,
+  /// Colors replacing the ones of the theme, in order.
+  pub roles:    Vec<(Role, Color)>,
+  /// Problems that did not stop the file from loading, such as fields that
+  /// are not compiled into this build.
+  pub warnings: Vec<ConfigError>,
+}
+
+impl Config {
+  fn style_mut(&mut self, field: &'static dyn Field) -> &mut FieldStyle {
+    let name = field.name();
+    if let Some(index) = self.styles.iter().position(|s| s.name == name) {
+      &mut self.styles[index]
+    } else {
+      self.styles.push(FieldStyle::new(name));
+      self.styles.last_mut().unwrap()
+    }
+  }
+
+  /// Parses a configuration file.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error on malformed lines, unknown keys, fields or colors.
+  pub fn parse(source: &str) -> Result<Self, ConfigError> {
+    let mut config = Self::default();
+
+    for (index, line) in source.lines().enumerate() {
+      let error = |kind| {
+        ConfigError::Syntax {
+          line: index + 1,
+          kind,
+        }
+      };
+      // The syntax knows every field, but some may be left out of this build
+      let mut find = |name: &str| {
+        let field = field::find(name);
+        if field.is_none() {
+          config.warnings.push(ConfigError::NotCompiledIn {
+            line: index + 1,
+            name: name.to_owned(),
+          });
+        }
+        field
+      };
+
+      let setting = match syntax::parse_line(line) {
+        Ok(Some(setting)) => setting,
+        Ok(None) => continue,
+        Err(kind) => return Err(error(kind)),
+      };
+
+      match setting {
+        Setting::Fields(names) => {
+          let fields = names.into_iter().filter_map(find).collect();
+          config.fields = Some(fields);
+        },
+        Setting::Theme(name) => {
+          config.theme = Some(theme::find(name).ok_or_else(|| {
+            error(SyntaxError::UnknownTheme(name.to_owned()))
+          })?);
+        },
+        Setting::Role(role, color) => config.roles.push((role, color)),
+        Setting::Style(name, property) => {
+          if let Some(field) = find(name) {
+            config.style_mut(field).apply(property);
+          }
+        },
+        // Only understood when baking a configuration in
+        Setting::Logo(_) => {
+          return Err(error(SyntaxError::UnknownKey("logo".to_owned())));
+        },
+        Setting::Palette(color, _) => {
+          return Err(error(SyntaxError::UnknownKey(format!(
+            "palette.{color}"
+          ))));
+        },
+      }
+    }
+
+    Ok(config)
+  }
+
+  /// The theme to render with: `theme` if given, e.g. from `--theme`, or else
+  /// the configured one, with the configured colors applied on top.
+  #[must_use]
+  pub fn resolve_theme(&self, theme: Option<&'static Theme>) -> Theme {
+    let mut theme = *theme.or(self.theme).unwrap_or(&theme::DEFAULT);
+    for &(role, color) in &self.roles {
+      theme.set(role, color);
+    }
+    theme
+  }
+
+  /// The configuration baked in at compile time, if any.
+  #[must_use]
+  pub fn baked() -> Option<Self> {
+    if !baked::ENABLED {
+      return None;
+    }
+
+    // build.rs has already left out the fields that are not compiled in
+    Some(Self {
+      fields:   baked::FIELDS.map(|names| {
+        names.iter().filter_map(|&name| field::find(name)).collect()
+      }),
+      styles:   baked::STYLES.to_vec(),
+      theme:    baked::THEME.and_then(theme::find),
+      roles:    baked::ROLES.to_vec(),
+      warnings: Vec::new(),
+    })
+  }
+
+  /// Loads the baked-in configuration or, if there is none, the
+  /// configuration file from its default location. A missing file is not an
+  /// error and yields the default configuration.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if the file exists but cannot be read or parsed.
+  #[cfg_attr(feature = "hotpath", hotpath::measure)]
+  pub fn load() -> Result<Self, ConfigError> {
+    if let Some(config) = Self::baked() {
+      return Ok(config);
+    }
+
+    let Some(path) = default_path() else {
+      return Ok(Self::default());
+    };
+
+    let mut buffer = [0u8; 4096];
+    let bytes_read = match read_file_fast(&path, &mut buffer) {
+      Ok(n) => n,
+      Err(err) if err.kind() == io::ErrorKind::NotFound => {
+        return Ok(Self::default());
+      },
+      Err(err) => return Err(ConfigError::Io(err)),
+    };
+    if bytes_read == buffer.len() {
+      return Err(ConfigError::TooLarge);
+    }
+
+    let source = std::str::from_utf8(&buffer[..bytes_read]).map_err(|_| {
+      ConfigError::Io(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "configuration is not valid UTF-8",
+      ))
+    })?;
+    Self::parse(source)
+  }
+}
+
+/// `$XDG_CONFIG_HOME/microfetch/config`, falling back to
+/// `$HOME/.config/microfetch/config`.
+#[must_use]
+pub fn default_path() -> Option<String> {
+  if let Some(config_home) = getenv(c"XDG_CONFIG_HOME") {
+    return Some(format!("{config_home}/microfetch/config"));
+  }
+  getenv(c"HOME").map(|home| format!("{home}/.config/microfetch/config"))
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+  Io(io::Error),
+  TooLarge,
+  Syntax {
+    line: usize,
+    kind: SyntaxError,
+  },
+  /// A known field that this build was compiled without.
+  NotCompiledIn {
+    line: usize,
+    name: String,
+  },
+}
+
+impl fmt::Display for ConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Io(err) => write!(f, "config: {err}"),
+      Self::TooLarge => write!(f, "config: file is too large"),
+      Self::Syntax { line, kind } => write!(f, "config:{line}: {kind}"),
+      Self::NotCompiledIn { line, name } => {
+        write!(
+          f,
+          "config:{line}: field '{name}' is not compiled in, ignoring it"
+        )
+      },
+    }
+  }
+}
+
+impl Error for ConfigError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      Self::Io(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn syntax_fields_match_registry() {
+    let compiled: Vec<_> =
+      field::REGISTRY.iter().map(|field| field.name()).collect();
+    let known: Vec<_> = syntax::FIELDS
+      .iter()
+      .filter(|&&(name, _)| field::find(name).is_some())
+      .map(|&(name, _)| name)
+      .collect();
+    assert_eq!(known, compiled);
+
+    // Fields that are not compiled in are the ones behind disabled features
+    for &(name, feature) in syntax::FIELDS {
+      if field::find(name).is_none() {
+        assert!(feature.is_some(), "'{name}' is missing from the registry");
+      }
+    }
+  }
+
+  #[test]
+  fn fields_not_compiled_in_are_skipped() {
+    let names: Vec<_> = syntax::FIELDS.iter().map(|&(name, _)| name).collect();
+    let labels = names.iter().map(|name| format!("{name}.label = Label\n"));
+    let source =
+      format!("fields = {}\n", names.join(", ")) + &labels.collect::<String>();
+    let config = Config::parse(&source).unwrap();
+
+    let missing = names.len() - field::REGISTRY.len();
+    assert_eq!(config.warnings.len(), 2 * missing);
+    assert_eq!(config.fields.unwrap().len(), field::REGISTRY.len());
+    assert_eq!(config.styles.len(), field::REGISTRY.len());
+  }
+
+  #[test]
+  fn bar_chars_are_one_column_wide() {
+    let bar_chars = |value: &str| {
+      match syntax::parse_line(&format!("memory.bar_chars = \"{value}\"")) {
+        Ok(Some(Setting::Style(_, Property::BarChars(chars)))) => Ok(chars),
+        Ok(setting) => panic!("unexpected {setting:?}"),
+        Err(err) => Err(err),
+      }
+    };
+    assert_eq!(bar_chars("#."), Ok(['#', '.']));
+    assert!(bar_chars("█░").is_ok());
+    for value in ["字.", "#", "#.-", "#\u{301}"] {
+      assert!(
+        matches!(bar_chars(value), Err(SyntaxError::InvalidValue { .. })),
+        "{value:?} was accepted"
+      );
+    }
+  }
+
+  #[test]
+  fn thresholds() {
+    assert_eq!(
+      Thresholds::parse("80, 95"),
+      Some(Thresholds::Levels {
+        warning:  80,
+        critical: 95,
+      })
+    );
+    assert_eq!(Thresholds::parse("none"), Some(Thresholds::Off));
+    for value in ["95, 80", "80", "80, 101", "80, 95, 99", ""] {
+      assert_eq!(Thresholds::parse(value), None, "{value:?} was accepted");
+    }
+
+    let role = |percent| Thresholds::DEFAULT.role(percent);
+    assert_eq!(role(69.0), Role::Good);
+    assert_eq!(role(70.0), Role::Warning);
+    assert_eq!(role(90.0), Role::Critical);
+    assert_eq!(Thresholds::Off.role(100.0), Role::Accent);
+  }
+
+  #[test]
+  fn syntax_themes_match_themes() {
+    let names: Vec<_> = theme::THEMES.iter().map(|theme| theme.name).collect();
+    assert_eq!(syntax::THEMES, names);
+  }
+
+  #[test]
+  fn parses_settings() {
+    let config = Config::parse(concat!(
+      "# Comment\n",
+      "\n",
+      "fields = os, , kernel\n",
+      "os.label = \" Distro \"\n",
+      "os.icon = D\n",
+      "os.icon_color = bright_red\n",
+      "  kernel.label_color  =  #fa0  \n",
+      "os.label = OS\n",
+      "theme = nord\n",
+      "theme.label = 208\n",
+      "theme.accent = #123456\n",
+    ))
+    .unwrap();
+
+    let names: Vec<_> = config
+      .fields
+      .unwrap()
+      .iter()
+      .map(|field| field.name())
+      .collect();
+    assert_eq!(names, ["os", "kernel"]);
+    assert!(config.warnings.is_empty());
+
+    let os = FieldStyle {
+      label: Some("OS".into()),
+      icon: Some("D".into()),
+      icon_color: Some(Color::BrightRed),
+      ..FieldStyle::new("os")
+    };
+    let kernel = FieldStyle {
+      label_color: Some(Color::Rgb(0xFF, 0xAA, 0x00)),
+      ..FieldStyle::new("kernel")
+    };
+    assert_eq!(config.styles, [os, kernel]);
+
+    let theme = Config {
+      theme: config.theme,
+      roles: config.roles,
+      ..Config::default()
+    }
+    .resolve_theme(None);
+    assert_eq!(theme.name, "nord");
+    assert_eq!(theme.label, Color::Fixed(208));
+    assert_eq!(theme.accent, Color::Rgb(0x12, 0x34, 0x56));
+    assert_eq!(theme.user, theme::find("nord").unwrap().user);
+  }
+
+  #[test]
+  fn reports_the_failing_line() {
+    let error = |source: &str| {
+      match Config::parse(source) {
+        Err(ConfigError::Syntax { line, kind }) => (line, kind),
+        Err(err) => panic!("unexpected {err}"),
+        Ok(_) => panic!("{source:?} was accepted"),
+      }
+    };
+    let unknown_key = |key: &str| SyntaxError::UnknownKey(key.to_owned());
+
+    assert_eq!(error("# ok\nfields"), (2, SyntaxError::MissingEquals));
+    assert_eq!(error("colour = red"), (1, unknown_key("colour")));
+    assert_eq!(error("os.colour = red"), (1, unknown_key("os.colour")));
+    assert_eq!(error("theme.sky = red"), (1, unknown_key("theme.sky")));
+    // Only a baked configuration may replace the logo or the palette
+    assert_eq!(error("logo = tux.txt"), (1, unknown_key("logo")));
+    assert_eq!(error("palette.red = 91"), (1, unknown_key("palette.red")));
+    // Usage properties only exist for fields showing a usage
+    assert_eq!(error("os.bar_width = 10"), (1, unknown_key("os.bar_width")));
+    assert_eq!(
+      error("fields = os, gpu"),
+      (1, SyntaxError::UnknownField("gpu".to_owned()))
+    );
+    assert_eq!(
+      error("theme = solarized"),
+      (1, SyntaxError::UnknownTheme("solarized".to_owned()))
+    );
+    assert_eq!(
+      error("os.icon_color = #12345"),
+      (1, SyntaxError::UnknownColor("#12345".to_owned()))
+    );
+
+    let err = Config::parse("\n\ntheme.user = 256").err().unwrap();
+    assert_eq!(err.to_string(), "config:3: unknown color '256'");
+  }
+
+  #[test]
+  fn colors() {
+    assert_eq!(Color::from_name("default"), Some(Color::Default));
+    assert_eq!(Color::from_name("bright_cyan"), Some(Color::BrightCyan));
+    assert_eq!(Color::from_name("0"), Some(Color::Fixed(0)));
+    assert_eq!(Color::from_name("255"), Some(Color::Fixed(255)));
+    assert_eq!(Color::from_name("#C0ffee"), Some(Color::Rgb(192, 255, 238)));
+    assert_eq!(Color::from_name("#abc"), Some(Color::Rgb(170, 187, 204)));
+    for name in ["", "Red", "256", "-1", "#", "#abcd", "#ggg", "#+1+2+3"] {
+      assert_eq!(Color::from_name(name), None, "{name:?} was accepted");
+    }
+  }
+}
@@ -0,0 +1,459 @@
+//! The configuration file format, shared by the runtime [`Config`] parser and
+//! `build.rs`, which includes this file to bake a configuration in.
+//!
+//! Everything here only depends on `std` and [`width`](crate::width), so that
+//! the two parsers cannot drift apart: the names of fields, themes, roles and
+//! colors, and how every value is read, are all defined once.
+//!
+//! [`Config`]: crate::config::Config
+
+use std::fmt;
+
+use crate::width::char_width;
+
+/// Every field that can be named in a configuration, with the cargo feature
+/// it needs, if any. Matches `field::REGISTRY` with all features enabled.
+pub const FIELDS: &[(&str, Option<&str>)] = &[
+  ("user", None),
+  ("os", None),
+  ("kernel", None),
+  ("shell", Some("shell")),
+  ("uptime", Some("uptime")),
+  ("desktop", Some("desktop")),
+  ("memory", Some("memory")),
+  ("storage", Some("storage")),
+  ("colors", Some("colors")),
+];
+
+/// The fields showing a usage, which take the usage properties.
+const USAGE_FIELDS: &[&str] = &["memory", "storage"];
+
+/// The names of `theme::THEMES`.
+pub const THEMES: &[&str] = &["default", "bright", "gruvbox", "mono", "nord"];
+
+/// Default SGR parameters of the 16 named colors and the reset sequence, which
+/// `build.rs` turns into the color macros of `colors.rs`. A baked
+/// configuration can override them with `palette.<color>`.
+pub const PALETTE: &[(&str, &str)] = &[
+  ("reset", "0"),
+  ("black", "30"),
+  ("red", "31"),
+  ("green", "32"),
+  ("yellow", "33"),
+  ("blue", "34"),
+  ("magenta", "35"),
+  ("cyan", "36"),
+  ("white", "37"),
+  ("bright_black", "90"),
+  ("bright_red", "91"),
+  ("bright_green", "92"),
+  ("bright_yellow", "93"),
+  ("bright_blue", "94"),
+  ("bright_magenta", "95"),
+  ("bright_cyan", "96"),
+  ("bright_white", "97"),
+];
+
+/// A foreground color, e.g. for a theme role or a field label. Colors can be
+/// referred to by name in the configuration file:
+///
+/// - the 16 named colors, e.g. `red` or `bright_red`,
+/// - `default`, the terminal's default color,
+/// - a number from 0 to 255 for the 256-color palette, and
+/// - `#rrggbb` or `#rgb` for truecolor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Color {
+  Default,
+  Black,
+  Red,
+  Green,
+  Yellow,
+  Blue,
+  Magenta,
+  Cyan,
+  White,
+  BrightBlack,
+  BrightRed,
+  BrightGreen,
+  BrightYellow,
+  BrightBlue,
+  BrightMagenta,
+  BrightCyan,
+  BrightWhite,
+  Fixed(u8),
+  Rgb(u8, u8, u8),
+}
+
+impl Color {
+  #[must_use]
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "default" => Some(Self::Default),
+      "black" => Some(Self::Black),
+      "red" => Some(Self::Red),
+      "green" => Some(Self::Green),
+      "yellow" => Some(Self::Yellow),
+      "blue" => Some(Self::Blue),
+      "magenta" => Some(Self::Magenta),
+      "cyan" => Some(Self::Cyan),
+      "white" => Some(Self::White),
+      "bright_black" => Some(Self::BrightBlack),
+      "bright_red" => Some(Self::BrightRed),
+      "bright_green" => Some(Self::BrightGreen),
+      "bright_yellow" => Some(Self::BrightYellow),
+      "bright_blue" => Some(Self::BrightBlue),
+      "bright_magenta" => Some(Self::BrightMagenta),
+      "bright_cyan" => Some(Self::BrightCyan),
+      "bright_white" => Some(Self::BrightWhite),
+      _ => {
+        if let Some(hex) = name.strip_prefix('#') {
+          return Self::from_hex(hex);
+        }
+        name.parse().ok().map(Self::Fixed)
+      },
+    }
+  }
+
+  /// Parses `rrggbb` or the shorthand `rgb`.
+  fn from_hex(hex: &str) -> Option<Self> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+      return None;
+    }
+    let channel = |digits: &str| u8::from_str_radix(digits, 16).ok();
+    match hex.len() {
+      6 => {
+        Some(Self::Rgb(
+          channel(&hex[0..2])?,
+          channel(&hex[2..4])?,
+          channel(&hex[4..6])?,
+        ))
+      },
+      // Every digit is doubled, e.g. `#fa0` is `#ffaa00`
+      3 => {
+        let [r, g, b] = [0, 1, 2].map(|i| channel(&hex[i..=i]).unwrap_or(0));
+        Some(Self::Rgb(r * 17, g * 17, b * 17))
+      },
+      _ => None,
+    }
+  }
+}
+
+/// A part of the fetch that a theme colors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+  /// `${c1}` in logos, i.e. their main shape.
+  LogoPrimary,
+  /// `${c2}` in logos.
+  LogoSecondary,
+  /// The user name in the title.
+  User,
+  /// The `@` between user and host name in the title.
+  At,
+  /// The host name in the title.
+  Host,
+  Icon,
+  Label,
+  /// The glyph between labels and values.
+  Separator,
+  Value,
+  /// Highlights within values, e.g. usage percentages.
+  Accent,
+  /// Usage percentages below their warning threshold.
+  Good,
+  /// Usage percentages at or above their warning threshold.
+  Warning,
+  /// Usage percentages at or above their critical threshold.
+  Critical,
+}
+
+impl Role {
+  #[must_use]
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "logo_primary" => Some(Self::LogoPrimary),
+      "logo_secondary" => Some(Self::LogoSecondary),
+      "user" => Some(Self::User),
+      "at" => Some(Self::At),
+      "host" => Some(Self::Host),
+      "icon" => Some(Self::Icon),
+      "label" => Some(Self::Label),
+      "separator" => Some(Self::Separator),
+      "value" => Some(Self::Value),
+      "accent" => Some(Self::Accent),
+      "good" => Some(Self::Good),
+      "warning" => Some(Self::Warning),
+      "critical" => Some(Self::Critical),
+      _ => None,
+    }
+  }
+}
+
+/// How a usage is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UsageDisplay {
+  /// `used GiB / total GiB (percent%)`.
+  #[default]
+  Text,
+  /// A bar and the percentage.
+  Bar,
+  /// The used and total sizes, followed by a bar and the percentage.
+  Both,
+}
+
+impl UsageDisplay {
+  #[must_use]
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "text" => Some(Self::Text),
+      "bar" => Some(Self::Bar),
+      "both" => Some(Self::Both),
+      _ => None,
+    }
+  }
+}
+
+/// Whether a usage is colored by how high it is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Thresholds {
+  /// Usages are always shown in the theme's accent color.
+  Off,
+  /// The usage percentages from which a usage is shown in the theme's warning
+  /// and critical colors, rather than in its good one.
+  Levels { warning: u8, critical: u8 },
+}
+
+impl Thresholds {
+  /// Warning from 70%, critical from 90%.
+  pub const DEFAULT: Self = Self::Levels {
+    warning:  70,
+    critical: 90,
+  };
+
+  /// Parses `warning, critical`, two percentages with the warning one first,
+  /// or `none`.
+  #[must_use]
+  pub fn parse(value: &str) -> Option<Self> {
+    if value == "none" {
+      return Some(Self::Off);
+    }
+    let (warning, critical) = value.split_once(',')?;
+    let percent = |value: &str| {
+      value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|&percent: &u8| percent <= 100)
+    };
+    let (warning, critical) = (percent(warning)?, percent(critical)?);
+    (warning <= critical).then_some(Self::Levels { warning, critical })
+  }
+
+  /// The role to color `percent` with.
+  #[must_use]
+  pub fn role(self, percent: f64) -> Role {
+    match self {
+      Self::Off => Role::Accent,
+      Self::Levels { critical, .. } if percent >= f64::from(critical) => {
+        Role::Critical
+      },
+      Self::Levels { warning, .. } if percent >= f64::from(warning) => {
+        Role::Warning
+      },
+      Self::Levels { .. } => Role::Good,
+    }
+  }
+}
+
+/// Parses exactly two characters, each one column wide.
+fn bar_chars(value: &str) -> Option<[char; 2]> {
+  let mut chars = value.chars();
+  match (chars.next(), chars.next(), chars.next()) {
+    (Some(filled), Some(empty), None)
+      if char_width(filled) == 1 && char_width(empty) == 1 =>
+    {
+      Some([filled, empty])
+    },
+    _ => None,
+  }
+}
+
+/// A single `key = value` line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Setting<'a> {
+  /// `fields`, the names of the fields to show, in order.
+  Fields(Vec<&'a str>),
+  /// `theme`, the name of the theme to start from.
+  Theme(&'a str),
+  /// `theme.<role>`, a color replacing the theme's.
+  Role(Role, Color),
+  /// `<field>.<property>`, with the name of the field.
+  Style(&'a str, Property<'a>),
+  /// `logo`, the path of a logo to bake in.
+  Logo(&'a str),
+  /// `palette.<color>`, the name of the color and its SGR parameters.
+  Palette(&'a str, &'a str),
+}
+
+/// A presentation override for a single field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property<'a> {
+  Label(&'a str),
+  Icon(&'a str),
+  IconColor(Color),
+  LabelColor(Color),
+  Thresholds(Thresholds),
+  Usage(UsageDisplay),
+  BarWidth(u8),
+  BarChars([char; 2]),
+}
+
+/// Strips a single pair of surrounding double quotes, if present.
+fn unquote(value: &str) -> &str {
+  value
+    .strip_prefix('"')
+    .and_then(|v| v.strip_suffix('"'))
+    .unwrap_or(value)
+}
+
+/// Checks that `name` is a field some build of microfetch knows about. Whether
+/// it is compiled into this one is up to the caller.
+fn field(name: &str) -> Result<&str, SyntaxError> {
+  if FIELDS.iter().any(|&(field, _)| field == name) {
+    Ok(name)
+  } else {
+    Err(SyntaxError::UnknownField(name.to_owned()))
+  }
+}
+
+/// Parses one line of a configuration file. `None` for blank lines and
+/// comments.
+///
+/// # Errors
+///
+/// Returns an error on malformed lines, unknown keys, fields or colors, and
+/// invalid values.
+pub fn parse_line(line: &str) -> Result<Option<Setting<'_>>, SyntaxError> {
+  let line = line.trim();
+  if line.is_empty() || line.starts_with('#') {
+    return Ok(None);
+  }
+
+  let (key, value) = line.split_once('=').ok_or(SyntaxError::MissingEquals)?;
+  let key = key.trim();
+  let value = unquote(value.trim());
+  let unknown_key = || SyntaxError::UnknownKey(key.to_owned());
+  let color = || {
+    Color::from_name(value)
+      .ok_or_else(|| SyntaxError::UnknownColor(value.to_owned()))
+  };
+
+  match key {
+    "fields" => {
+      let fields = value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(field)
+        .collect::<Result<_, _>>()?;
+      return Ok(Some(Setting::Fields(fields)));
+    },
+    "theme" => {
+      if !THEMES.contains(&value) {
+        return Err(SyntaxError::UnknownTheme(value.to_owned()));
+      }
+      return Ok(Some(Setting::Theme(value)));
+    },
+    "logo" => return Ok(Some(Setting::Logo(value))),
+    _ => {},
+  }
+
+  let (scope, property) = key.split_once('.').ok_or_else(unknown_key)?;
+  match scope {
+    "theme" => {
+      let role = Role::from_name(property).ok_or_else(unknown_key)?;
+      return Ok(Some(Setting::Role(role, color()?)));
+    },
+    "palette" => {
+      if !PALETTE.iter().any(|&(name, _)| name == property) {
+        return Err(SyntaxError::UnknownColor(property.to_owned()));
+      }
+      if value.is_empty()
+        || !value.bytes().all(|b| b.is_ascii_digit() || b == b';')
+      {
+        return Err(SyntaxError::InvalidValue {
+          key:   key.to_owned(),
+          value: value.to_owned(),
+        });
+      }
+      return Ok(Some(Setting::Palette(property, value)));
+    },
+    _ => {},
+  }
+
+  let name = field(scope)?;
+  let usage_field = USAGE_FIELDS.contains(&name);
+  let invalid = || {
+    SyntaxError::InvalidValue {
+      key:   key.to_owned(),
+      value: value.to_owned(),
+    }
+  };
+
+  let property = match property {
+    "label" => Property::Label(value),
+    "icon" => Property::Icon(value),
+    "icon_color" => Property::IconColor(color()?),
+    "label_color" => Property::LabelColor(color()?),
+    "thresholds" if usage_field => {
+      Property::Thresholds(
+        Thresholds::parse(value)
+          .ok_or_else(|| SyntaxError::InvalidThresholds(value.to_owned()))?,
+      )
+    },
+    "usage" if usage_field => {
+      Property::Usage(UsageDisplay::from_name(value).ok_or_else(invalid)?)
+    },
+    "bar_width" if usage_field => {
+      let width = value.parse().ok().filter(|w| (1..=100).contains(w));
+      Property::BarWidth(width.ok_or_else(invalid)?)
+    },
+    "bar_chars" if usage_field => {
+      Property::BarChars(bar_chars(value).ok_or_else(invalid)?)
+    },
+    _ => return Err(unknown_key()),
+  };
+  Ok(Some(Setting::Style(name, property)))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxError {
+  MissingEquals,
+  UnknownKey(String),
+  UnknownField(String),
+  UnknownColor(String),
+  UnknownTheme(String),
+  InvalidThresholds(String),
+  InvalidValue { key: String, value: String },
+}
+
+impl fmt::Display for SyntaxError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::MissingEquals => write!(f, "expected 'key = value'"),
+      Self::UnknownKey(key) => write!(f, "unknown key '{key}'"),
+      Self::UnknownField(name) => write!(f, "unknown field '{name}'"),
+      Self::UnknownColor(name) => write!(f, "unknown color '{name}'"),
+      Self::UnknownTheme(name) => write!(f, "unknown theme '{name}'"),
+      Self::InvalidThresholds(value) => {
+        write!(
+          f,
+          "invalid thresholds '{value}', expected 'warning, critical' or \
+           'none'"
+        )
+      },
+      Self::InvalidValue { key, value } => {
+        write!(f, "invalid value '{value}' for '{key}'")
+      },
+    }
+  }
+}
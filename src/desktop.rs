@@ -1,45 +1,47 @@
-use std::ffi::CStr;
+use std::io;
 
-use crate::unknown;
+use crate::{
+  field::{Context, Field, Value},
+  getenv,
+};
+
+/// The running desktop environment and its display backend, as advertised by
+/// `XDG_CURRENT_DESKTOP` and `XDG_SESSION_TYPE`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DesktopInfo {
+  pub desktop:      Option<String>,
+  pub session_type: Option<String>,
+}
 
 #[must_use]
 #[cfg_attr(feature = "hotpath", hotpath::measure)]
-pub fn get_desktop_info() -> String {
-  // Retrieve the environment variables and handle Result types
-  let desktop_str = unsafe {
-    let ptr = libc::getenv(c"XDG_CURRENT_DESKTOP".as_ptr());
-    if ptr.is_null() {
-      unknown()
-    } else {
-      let s = CStr::from_ptr(ptr).to_str().unwrap_or_else(|_| unknown());
-      s.strip_prefix("none+").unwrap_or(s)
-    }
-  };
-
-  let backend_str = unsafe {
-    let ptr = libc::getenv(c"XDG_SESSION_TYPE".as_ptr());
-    if ptr.is_null() {
-      unknown()
-    } else {
-      let s = CStr::from_ptr(ptr).to_str().unwrap_or_else(|_| unknown());
-      if s.is_empty() { unknown() } else { s }
-    }
-  };
-
-  // Pre-calculate capacity: desktop_len + " (" + backend_len + ")"
-  // Capitalize first char needs temporary allocation only if backend exists
-  let mut result =
-    String::with_capacity(desktop_str.len() + backend_str.len() + 3);
-  result.push_str(desktop_str);
-  result.push(' ');
-  result.push('(');
-
-  // Capitalize first character of backend
-  if let Some(first_char) = backend_str.chars().next() {
-    result.push(first_char.to_ascii_uppercase());
-    result.push_str(&backend_str[first_char.len_utf8()..]);
+pub fn get_desktop_info() -> DesktopInfo {
+  let desktop = getenv(c"XDG_CURRENT_DESKTOP")
+    .map(|s| s.strip_prefix("none+").unwrap_or(s).to_owned());
+  let session_type = getenv(c"XDG_SESSION_TYPE").map(str::to_owned);
+
+  DesktopInfo {
+    desktop,
+    session_type,
+  }
+}
+
+pub struct DesktopField;
+
+impl Field for DesktopField {
+  fn name(&self) -> &'static str {
+    "desktop"
+  }
+
+  fn label(&self) -> &'static str {
+    "Desktop"
   }
 
-  result.push(')');
-  result
+  fn icon(&self) -> &'static str {
+    "\u{F2D2}"
+  }
+
+  fn collect(&self, _: &Context) -> Result<Value, io::Error> {
+    Ok(Value::Desktop(get_desktop_info()))
+  }
 }
@@ -1,9 +1,17 @@
-use crate::{colors::IS_NO_COLOR, BLUE, CYAN, GREEN, MAGENTA, RED, RESET, YELLOW};
+use std::io;
 
-macro_rules! GLYPH  {() => {""}}
+use crate::{
+  colors::IS_NO_COLOR,
+  field::{Context, Field, Value},
+};
 
+#[rustfmt::skip]
+macro_rules! GLYPH  {() => {"\u{F111}"}}
+
+#[rustfmt::skip]
 macro_rules! GAP    {() => {"  "}}
 
+#[rustfmt::skip]
 const NO_COLORS_STR: &str = concat!(
   GLYPH!(), GAP!(),
   GLYPH!(), GAP!(),
@@ -13,6 +21,7 @@ const NO_COLORS_STR: &str = concat!(
   GLYPH!(),
 );
 
+#[rustfmt::skip]
 const COLORS_STR: &str = concat!(
   BLUE!(),    GLYPH!(), GAP!(),
   CYAN!(),    GLYPH!(), GAP!(),
@@ -31,3 +40,23 @@ pub fn print_dots() -> &'static str {
     COLORS_STR
   }
 }
+
+pub struct ColorsField;
+
+impl Field for ColorsField {
+  fn name(&self) -> &'static str {
+    "colors"
+  }
+
+  fn label(&self) -> &'static str {
+    "Colors"
+  }
+
+  fn icon(&self) -> &'static str {
+    "\u{E22B}"
+  }
+
+  fn collect(&self, _: &Context) -> Result<Value, io::Error> {
+    Ok(Value::Palette)
+  }
+}
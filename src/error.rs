@@ -0,0 +1,30 @@
+use std::{error::Error, fmt, io};
+
+/// A failure to collect a single field. The fetch carries on without the
+/// field, rendering a placeholder in its place, and the error is kept around
+/// so that it can be reported on request.
+#[derive(Debug)]
+pub struct FieldError {
+  /// Name of the field that could not be collected, e.g. `memory`.
+  pub field:  &'static str,
+  pub source: io::Error,
+}
+
+impl FieldError {
+  #[must_use]
+  pub const fn new(field: &'static str, source: io::Error) -> Self {
+    Self { field, source }
+  }
+}
+
+impl fmt::Display for FieldError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}: {}", self.field, self.source)
+  }
+}
+
+impl Error for FieldError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    Some(&self.source)
+  }
+}
@@ -0,0 +1,130 @@
+//! The field registry. Every module that knows how to collect a piece of
+//! information exposes it as a [`Field`], and [`REGISTRY`] lists them in the
+//! order they are displayed by default. Adding, removing or reordering fields
+//! only means touching that list.
+
+use std::io;
+
+#[cfg(feature = "desktop")]
+use crate::desktop::{DesktopField, DesktopInfo};
+#[cfg(feature = "colors")] use crate::dots::ColorsField;
+#[cfg(feature = "shell")] use crate::system::ShellField;
+#[cfg(feature = "storage")]
+use crate::system::{DiskUsage, StorageField};
+#[cfg(feature = "memory")]
+use crate::system::{MemoryField, MemoryInfo};
+#[cfg(feature = "uptime")]
+use crate::uptime::{Uptime, UptimeField};
+use crate::{
+  Root,
+  UtsName,
+  release::{KernelField, KernelInfo, OsField, OsRelease},
+  system::{UserField, UserInfo},
+};
+
+/// A single piece of information shown in the fetch.
+pub trait Field: Sync {
+  /// Stable identifier used to select the field, e.g. `memory`.
+  fn name(&self) -> &'static str;
+
+  /// Human-readable label, e.g. `Memory`. Fields with an empty label are
+  /// rendered as a title line rather than as a labelled row.
+  fn label(&self) -> &'static str;
+
+  /// Nerd Font glyph shown in front of the label.
+  fn icon(&self) -> &'static str;
+
+  /// Collects the value of this field.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if the underlying syscall or file read fails.
+  fn collect(&self, ctx: &Context) -> Result<Value, io::Error>;
+}
+
+/// The typed value collected by a [`Field`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+  User(UserInfo),
+  Os(OsRelease),
+  Kernel(KernelInfo),
+  #[cfg(feature = "desktop")]
+  Desktop(DesktopInfo),
+  #[cfg(feature = "uptime")]
+  Uptime(Uptime),
+  #[cfg(feature = "memory")]
+  Memory(MemoryInfo),
+  #[cfg(feature = "storage")]
+  Disk(DiskUsage),
+  /// The terminal color palette. Carries no data, it is purely rendered.
+  #[cfg(feature = "colors")]
+  Palette,
+  Text(String),
+}
+
+/// State shared between collectors, so that e.g. `uname` is only called once
+/// no matter how many fields need it.
+pub struct Context {
+  utsname: Result<UtsName, i32>,
+  root:    Root,
+}
+
+impl Context {
+  #[must_use]
+  #[cfg_attr(feature = "hotpath", hotpath::measure)]
+  pub fn new(root: Root) -> Self {
+    let utsname =
+      UtsName::uname().map_err(|err| err.raw_os_error().unwrap_or(libc::EIO));
+    Self { utsname, root }
+  }
+
+  /// The root that system files are read from.
+  #[must_use]
+  pub const fn root(&self) -> &Root {
+    &self.root
+  }
+
+  /// The result of calling `uname`.
+  ///
+  /// # Errors
+  ///
+  /// Returns the error `uname` failed with, if any.
+  pub fn uname(&self) -> Result<&UtsName, io::Error> {
+    self
+      .utsname
+      .as_ref()
+      .map_err(|&errno| io::Error::from_raw_os_error(errno))
+  }
+}
+
+impl Default for Context {
+  fn default() -> Self {
+    Self::new(Root::default())
+  }
+}
+
+/// Every known field, in default display order. Fields whose cargo feature
+/// is disabled are compiled out entirely.
+pub static REGISTRY: &[&dyn Field] = &[
+  &UserField,
+  &OsField,
+  &KernelField,
+  #[cfg(feature = "shell")]
+  &ShellField,
+  #[cfg(feature = "uptime")]
+  &UptimeField,
+  #[cfg(feature = "desktop")]
+  &DesktopField,
+  #[cfg(feature = "memory")]
+  &MemoryField,
+  #[cfg(feature = "storage")]
+  &StorageField,
+  #[cfg(feature = "colors")]
+  &ColorsField,
+];
+
+/// Looks up a registered field by its [`Field::name`].
+#[must_use]
+pub fn find(name: &str) -> Option<&'static dyn Field> {
+  REGISTRY.iter().copied().find(|field| field.name() == name)
+}
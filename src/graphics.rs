@@ -0,0 +1,228 @@
+//! Draws an [`Image`] with the terminal's graphics protocol, as a raster
+//! alternative to the text logos.
+//!
+//! The text layout only needs to know how many cells the image covers: a blank
+//! [`placeholder`](Graphic::placeholder) logo of that size keeps its place, and
+//! the image is drawn over it once the text is out.
+
+use std::fmt::Write as _;
+
+use crate::{getenv, image::Image, logo::Logo, terminal};
+
+/// Cell size assumed when the terminal does not report one, in pixels.
+const DEFAULT_CELL: (usize, usize) = (8, 16);
+
+/// Base64 payload bytes per kitty graphics escape sequence.
+const KITTY_CHUNK: usize = 4096;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Protocol {
+  /// The kitty graphics protocol, also spoken by `WezTerm`, Ghostty and
+  /// Konsole.
+  Kitty,
+  /// DEC sixel graphics, limited to 216 colors here.
+  Sixel,
+}
+
+impl Protocol {
+  #[must_use]
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "kitty" => Some(Self::Kitty),
+      "sixel" => Some(Self::Sixel),
+      _ => None,
+    }
+  }
+
+  /// Guesses the protocol from the environment: kitty for terminals known to
+  /// support it, and sixel for everything else.
+  #[must_use]
+  pub fn detect() -> Self {
+    let term = getenv(c"TERM").unwrap_or_default();
+    let program = getenv(c"TERM_PROGRAM").unwrap_or_default();
+    if getenv(c"KITTY_WINDOW_ID").is_some()
+      || term.contains("kitty")
+      || term.contains("ghostty")
+      || matches!(program, "WezTerm" | "ghostty")
+    {
+      Self::Kitty
+    } else {
+      Self::Sixel
+    }
+  }
+}
+
+/// An image encoded for the terminal.
+pub struct Graphic {
+  /// Escape sequence drawing the image at the cursor.
+  pub sequence: String,
+  /// Number of cells the image covers horizontally.
+  pub columns:  usize,
+  /// Number of cells the image covers vertically.
+  pub rows:     usize,
+}
+
+impl Graphic {
+  /// Encodes `image` for `protocol`, at its natural size.
+  #[must_use]
+  #[cfg_attr(feature = "hotpath", hotpath::measure)]
+  pub fn new(image: &Image, protocol: Protocol) -> Self {
+    let (cell_width, cell_height) =
+      terminal::cell_size().unwrap_or(DEFAULT_CELL);
+    Self {
+      sequence: match protocol {
+        Protocol::Kitty => kitty(image),
+        Protocol::Sixel => sixel(image),
+      },
+      columns:  (image.width as usize).div_ceil(cell_width),
+      rows:     (image.height as usize).div_ceil(cell_height),
+    }
+  }
+
+  /// A blank logo covering the same cells as the image.
+  ///
+  /// The logo is leaked, like the ones from [`crate::logo::load`].
+  #[must_use]
+  pub fn placeholder(&self) -> &'static Logo {
+    let line: &'static str = " ".repeat(self.columns).leak();
+    Box::leak(Box::new(Logo {
+      name:  "image",
+      lines: vec![line; self.rows].leak(),
+      small: None,
+    }))
+  }
+}
+
+fn base64(bytes: &[u8]) -> String {
+  const ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+  let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+  for chunk in bytes.chunks(3) {
+    let n = chunk
+      .iter()
+      .enumerate()
+      .fold(0u32, |n, (i, &byte)| n | u32::from(byte) << (16 - 8 * i));
+    for i in 0..4 {
+      if i <= chunk.len() {
+        encoded.push(char::from(ALPHABET[(n >> (18 - 6 * i)) as usize & 63]));
+      } else {
+        encoded.push('=');
+      }
+    }
+  }
+  encoded
+}
+
+/// Transmits and displays the raw RGBA pixels in one go, split into chunks as
+/// the protocol requires. The cursor is left where it was, and the terminal
+/// is asked not to reply.
+fn kitty(image: &Image) -> String {
+  let payload = base64(&image.pixels);
+  let mut sequence = String::with_capacity(payload.len() + 64);
+
+  let mut chunks = payload.as_bytes().chunks(KITTY_CHUNK).peekable();
+  let mut first = true;
+  while let Some(chunk) = chunks.next() {
+    let more = u8::from(chunks.peek().is_some());
+    if first {
+      write!(
+        sequence,
+        "\x1b_Ga=T,f=32,s={},v={},C=1,q=2,m={more};",
+        image.width, image.height,
+      )
+      .unwrap();
+      first = false;
+    } else {
+      write!(sequence, "\x1b_Gm={more};").unwrap();
+    }
+    // Base64 is plain ASCII
+    sequence.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+    sequence.push_str("\x1b\\");
+  }
+  sequence
+}
+
+/// Maps a pixel onto the 6×6×6 color cube used for sixel output, or `None`
+/// if it is mostly transparent.
+fn cube([r, g, b, a]: [u8; 4]) -> Option<usize> {
+  let level = |channel: u8| (usize::from(channel) * 5 + 127) / 255;
+  (a >= 128).then(|| level(r) * 36 + level(g) * 6 + level(b))
+}
+
+/// Writes `count` repetitions of a sixel, compressed when it pays off.
+fn push_run(sequence: &mut String, sixel: char, count: usize) {
+  if count > 3 {
+    write!(sequence, "!{count}{sixel}").unwrap();
+  } else {
+    sequence.extend(std::iter::repeat_n(sixel, count));
+  }
+}
+
+/// Encodes the image as sixels. Sixel output is palette based, so colors are
+/// reduced to a 6×6×6 cube, and transparent pixels keep the background.
+fn sixel(image: &Image) -> String {
+  let width = image.width as usize;
+  let colors: Vec<Option<usize>> = (0..image.height)
+    .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+    .map(|(x, y)| cube(image.pixel(x, y)))
+    .collect();
+
+  // Select transparent background, then give the size in pixels
+  let mut sequence = String::from("\x1bP0;1;0q");
+  write!(sequence, "\"1;1;{};{}", image.width, image.height).unwrap();
+
+  let mut used = [false; 216];
+  for &color in colors.iter().flatten() {
+    used[color] = true;
+  }
+  for (color, _) in used.iter().enumerate().filter(|&(_, &used)| used) {
+    let percent = |level: usize| level * 100 / 5;
+    write!(
+      sequence,
+      "#{color};2;{};{};{}",
+      percent(color / 36),
+      percent(color / 6 % 6),
+      percent(color % 6),
+    )
+    .unwrap();
+  }
+
+  // Every band of six pixel rows is drawn once per color it contains
+  for band in colors.chunks(width * 6) {
+    let mut in_band = [false; 216];
+    for &color in band.iter().flatten() {
+      in_band[color] = true;
+    }
+
+    for (color, _) in in_band.iter().enumerate().filter(|&(_, &used)| used) {
+      write!(sequence, "#{color}").unwrap();
+      let mut run = ('?', 0);
+      for x in 0..width {
+        let mut bits = 0u8;
+        for (dy, row) in band.chunks(width).enumerate() {
+          if row[x] == Some(color) {
+            bits |= 1 << dy;
+          }
+        }
+        let sixel = char::from(b'?' + bits);
+        if sixel == run.0 {
+          run.1 += 1;
+        } else {
+          push_run(&mut sequence, run.0, run.1);
+          run = (sixel, 1);
+        }
+      }
+      // Trailing empty sixels can be left out
+      if run.0 != '?' {
+        push_run(&mut sequence, run.0, run.1);
+      }
+      // Back to the start of the band for the next color
+      sequence.push('$');
+    }
+    sequence.push('-');
+  }
+
+  sequence.push_str("\x1b\\");
+  sequence
+}
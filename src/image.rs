@@ -0,0 +1,299 @@
+//! A tiny decoder for raster logos, so that no image crate is needed. Two
+//! simple formats are understood:
+//!
+//! - binary PPM (`P6`), with at most 8 bits per channel, and
+//! - [QOI](https://qoiformat.org), which unlike PPM supports transparency.
+
+use std::io;
+
+use crate::syscall::read_file_to_end;
+
+/// Largest accepted width and height, in pixels. Logos are drawn next to a
+/// handful of text lines, so anything bigger is almost certainly a mistake.
+const MAX_DIMENSION: u32 = 4096;
+
+/// A decoded image.
+pub struct Image {
+  pub width:  u32,
+  pub height: u32,
+  /// Pixels in rows from top to bottom, as 8-bit RGBA.
+  pub pixels: Vec<u8>,
+}
+
+impl Image {
+  /// The RGBA value of the pixel at `x`, `y`.
+  #[must_use]
+  pub fn pixel(&self, x: u32, y: u32) -> [u8; 4] {
+    let start = (y as usize * self.width as usize + x as usize) * 4;
+    let mut pixel = [0; 4];
+    pixel.copy_from_slice(&self.pixels[start..start + 4]);
+    pixel
+  }
+}
+
+fn invalid(message: &'static str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Checks the dimensions from a header and allocates room for the pixels.
+fn pixels_for(width: u32, height: u32) -> io::Result<Vec<u8>> {
+  if width == 0 || height == 0 {
+    return Err(invalid("image is empty"));
+  }
+  if width > MAX_DIMENSION || height > MAX_DIMENSION {
+    return Err(invalid("image is too large"));
+  }
+  Ok(Vec::with_capacity(width as usize * height as usize * 4))
+}
+
+/// Decodes a PPM or QOI image, told apart by their magic bytes.
+///
+/// # Errors
+///
+/// Returns an error if `bytes` is neither format, or is truncated or
+/// malformed.
+pub fn decode(bytes: &[u8]) -> io::Result<Image> {
+  if bytes.starts_with(b"qoif") {
+    decode_qoi(bytes)
+  } else if bytes.starts_with(b"P6") {
+    decode_ppm(bytes)
+  } else {
+    Err(invalid(
+      "unsupported image format, expected QOI or binary PPM",
+    ))
+  }
+}
+
+/// Reads the next number from a PPM header, skipping whitespace and comments.
+fn ppm_number(bytes: &[u8], pos: &mut usize) -> io::Result<u32> {
+  loop {
+    match bytes.get(*pos) {
+      Some(b) if b.is_ascii_whitespace() => *pos += 1,
+      Some(b'#') => {
+        while bytes.get(*pos).is_some_and(|&b| b != b'\n') {
+          *pos += 1;
+        }
+      },
+      _ => break,
+    }
+  }
+
+  let start = *pos;
+  while bytes.get(*pos).is_some_and(u8::is_ascii_digit) {
+    *pos += 1;
+  }
+  std::str::from_utf8(&bytes[start..*pos])
+    .ok()
+    .and_then(|digits| digits.parse().ok())
+    .ok_or_else(|| invalid("invalid PPM header"))
+}
+
+fn decode_ppm(bytes: &[u8]) -> io::Result<Image> {
+  let mut pos = 2;
+  let width = ppm_number(bytes, &mut pos)?;
+  let height = ppm_number(bytes, &mut pos)?;
+  let max = ppm_number(bytes, &mut pos)?;
+  if max == 0 || max > 255 {
+    return Err(invalid("unsupported PPM bit depth"));
+  }
+  // A single whitespace character separates the header from the pixels
+  if !bytes.get(pos).is_some_and(u8::is_ascii_whitespace) {
+    return Err(invalid("invalid PPM header"));
+  }
+  pos += 1;
+
+  let mut pixels = pixels_for(width, height)?;
+  let data = bytes
+    .get(pos..pos + width as usize * height as usize * 3)
+    .ok_or_else(|| invalid("truncated PPM image"))?;
+  for rgb in data.chunks_exact(3) {
+    for &channel in rgb {
+      // Scale to the full 8-bit range
+      #[allow(clippy::cast_possible_truncation)]
+      pixels.push((u32::from(channel).min(max) * 255 / max) as u8);
+    }
+    pixels.push(255);
+  }
+
+  Ok(Image {
+    width,
+    height,
+    pixels,
+  })
+}
+
+const QOI_OP_INDEX: u8 = 0x00;
+const QOI_OP_DIFF: u8 = 0x40;
+const QOI_OP_LUMA: u8 = 0x80;
+const QOI_OP_RGB: u8 = 0xFE;
+const QOI_OP_RGBA: u8 = 0xFF;
+
+const fn qoi_hash([r, g, b, a]: [u8; 4]) -> usize {
+  (r as usize * 3 + g as usize * 5 + b as usize * 7 + a as usize * 11) % 64
+}
+
+fn decode_qoi(bytes: &[u8]) -> io::Result<Image> {
+  let header = bytes
+    .get(..14)
+    .ok_or_else(|| invalid("truncated QOI header"))?;
+  let width = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+  let height =
+    u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+  if !matches!(header[12], 3 | 4) {
+    return Err(invalid("invalid QOI header"));
+  }
+
+  let mut pixels = pixels_for(width, height)?;
+  let total = width as usize * height as usize * 4;
+
+  let mut index = [[0u8; 4]; 64];
+  let mut pixel = [0, 0, 0, 255];
+  let mut data = bytes[14..].iter().copied();
+  let mut next = || data.next().ok_or_else(|| invalid("truncated QOI image"));
+
+  while pixels.len() < total {
+    let op = next()?;
+    let mut run = 1;
+    match op {
+      QOI_OP_RGB => {
+        pixel[0] = next()?;
+        pixel[1] = next()?;
+        pixel[2] = next()?;
+      },
+      QOI_OP_RGBA => {
+        pixel = [next()?, next()?, next()?, next()?];
+      },
+      _ => {
+        match op & 0xC0 {
+          QOI_OP_INDEX => pixel = index[usize::from(op)],
+          QOI_OP_DIFF => {
+            pixel[0] = pixel[0].wrapping_add((op >> 4) & 3).wrapping_sub(2);
+            pixel[1] = pixel[1].wrapping_add((op >> 2) & 3).wrapping_sub(2);
+            pixel[2] = pixel[2].wrapping_add(op & 3).wrapping_sub(2);
+          },
+          QOI_OP_LUMA => {
+            let second = next()?;
+            let dg = (op & 0x3F).wrapping_sub(32);
+            pixel[0] = pixel[0]
+              .wrapping_add(dg)
+              .wrapping_add(second >> 4)
+              .wrapping_sub(8);
+            pixel[1] = pixel[1].wrapping_add(dg);
+            pixel[2] = pixel[2]
+              .wrapping_add(dg)
+              .wrapping_add(second & 0x0F)
+              .wrapping_sub(8);
+          },
+          // QOI_OP_RUN, 0xC0
+          _ => run = usize::from(op & 0x3F) + 1,
+        }
+      },
+    }
+
+    index[qoi_hash(pixel)] = pixel;
+    for _ in 0..run {
+      if pixels.len() == total {
+        break;
+      }
+      pixels.extend_from_slice(&pixel);
+    }
+  }
+
+  Ok(Image {
+    width,
+    height,
+    pixels,
+  })
+}
+
+/// Reads and decodes an image file, see [`decode`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or decoded.
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn load(path: &str) -> io::Result<Image> {
+  let mut bytes = Vec::new();
+  read_file_to_end(path, &mut bytes)?;
+  decode(&bytes)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn decodes_ppm() {
+    let mut bytes = b"P6\n# made by hand\n2 1\n15\n".to_vec();
+    bytes.extend([15, 0, 7, 16, 15, 15]);
+    let image = decode(&bytes).unwrap();
+
+    assert_eq!((image.width, image.height), (2, 1));
+    // Channels are scaled from 0..=15 to 0..=255, and clamped
+    assert_eq!(image.pixels, [255, 0, 119, 255, 255, 255, 255, 255]);
+    assert_eq!(image.pixel(1, 0), [255, 255, 255, 255]);
+  }
+
+  #[test]
+  fn decodes_qoi() {
+    let mut bytes = b"qoif".to_vec();
+    bytes.extend(3u32.to_be_bytes());
+    bytes.extend(2u32.to_be_bytes());
+    bytes.extend([4, 0]);
+    bytes.extend([
+      QOI_OP_RGB,
+      10,
+      20,
+      30,   // A new color
+      0x76, // QOI_OP_DIFF by 1, -1 and 0
+      0xA5,
+      0xA5, // QOI_OP_LUMA by 5 + 2, 5 and 5 - 3
+      QOI_OP_RGBA,
+      1,
+      2,
+      3,
+      4,    // A new color and alpha
+      0x09, // QOI_OP_INDEX of the first color
+      0xC4, // QOI_OP_RUN of 5, cut short by the end of the image
+    ]);
+    bytes.extend([0, 0, 0, 0, 0, 0, 0, 1]);
+    let image = decode(&bytes).unwrap();
+
+    assert_eq!((image.width, image.height), (3, 2));
+    assert_eq!(image.pixels, [
+      10, 20, 30, 255, 11, 19, 30, 255, 18, 24, 32, 255, //
+      1, 2, 3, 4, 10, 20, 30, 255, 10, 20, 30, 255,
+    ]);
+    assert_eq!(image.pixel(0, 1), [1, 2, 3, 4]);
+  }
+
+  #[test]
+  fn rejects_malformed_images() {
+    let qoi = |channels: u8, data: &[u8]| {
+      let mut bytes = b"qoif".to_vec();
+      bytes.extend(1u32.to_be_bytes());
+      bytes.extend(2u32.to_be_bytes());
+      bytes.extend([channels, 0]);
+      bytes.extend(data);
+      bytes
+    };
+
+    for (bytes, message) in [
+      (b"GIF89a".to_vec(), "unsupported image format"),
+      (b"P6 0 1 255\n".to_vec(), "image is empty"),
+      (b"P6 4097 1 255\n".to_vec(), "image is too large"),
+      (b"P6 1 1 256\n".to_vec(), "unsupported PPM bit depth"),
+      (b"P6 1 1 255".to_vec(), "invalid PPM header"),
+      (b"P6 1 x 255\n".to_vec(), "invalid PPM header"),
+      (b"P6 1 1 255\n\0\0".to_vec(), "truncated PPM image"),
+      (b"qoif\0\0\0\x01".to_vec(), "truncated QOI header"),
+      (qoi(5, &[]), "invalid QOI header"),
+      (qoi(3, &[QOI_OP_RGB, 1, 2, 3]), "truncated QOI image"),
+      (qoi(3, &[QOI_OP_RGB, 1, 2]), "truncated QOI image"),
+    ] {
+      let err = decode(&bytes).err().unwrap();
+      assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+      assert!(err.to_string().starts_with(message), "{err} for {bytes:?}");
+    }
+  }
+}
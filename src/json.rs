@@ -0,0 +1,618 @@
+//! Hand-written JSON serialization of the collected fields, so that we do not
+//! need to pull in serde for a single flat object.
+//!
+//! The output follows [`SCHEMA`]. Its `version` is bumped whenever a field is
+//! removed or changes meaning; adding fields is not considered a breaking
+//! change.
+//!
+//! A matching [`parse`] reads the output back for snapshots. It only accepts
+//! what the schema can contain, so numbers must be non-negative integers.
+
+use std::{error::Error, fmt, fmt::Write as _};
+
+use crate::{Fields, field::Value};
+
+/// Version of the JSON output, see [`SCHEMA`].
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// JSON Schema describing the output of [`to_json`].
+pub const SCHEMA: &str = r#"{
+  "$schema": "https://json-schema.org/draft/2020-12/schema",
+  "title": "microfetch",
+  "type": "object",
+  "required": ["version", "fields", "errors"],
+  "properties": {
+    "version": { "const": 1 },
+    "fields": {
+      "type": "object",
+      "description": "Collected fields, in display order. A field that failed to collect is null.",
+      "properties": {
+        "user": {
+          "type": ["object", "null"],
+          "properties": {
+            "username": { "type": "string" },
+            "hostname": { "type": "string" }
+          }
+        },
+        "os": { "type": ["string", "null"] },
+        "kernel": {
+          "type": ["object", "null"],
+          "properties": {
+            "sysname": { "type": "string" },
+            "release": { "type": "string" },
+            "machine": { "type": "string" }
+          }
+        },
+        "shell": { "type": ["string", "null"] },
+        "uptime": {
+          "type": ["object", "null"],
+          "properties": {
+            "seconds": { "type": "integer", "minimum": 0 }
+          }
+        },
+        "desktop": {
+          "type": ["object", "null"],
+          "properties": {
+            "desktop": { "type": ["string", "null"] },
+            "session_type": { "type": ["string", "null"] }
+          }
+        },
+        "memory": {
+          "type": ["object", "null"],
+          "description": "Sizes in bytes.",
+          "properties": {
+            "total": { "type": "integer", "minimum": 0 },
+            "available": { "type": "integer", "minimum": 0 },
+            "used": { "type": "integer", "minimum": 0 }
+          }
+        },
+        "storage": {
+          "type": ["object", "null"],
+          "description": "Sizes in bytes.",
+          "properties": {
+            "mount_point": { "type": "string" },
+            "total": { "type": "integer", "minimum": 0 },
+            "free": { "type": "integer", "minimum": 0 },
+            "available": { "type": "integer", "minimum": 0 },
+            "used": { "type": "integer", "minimum": 0 }
+          }
+        }
+      }
+    },
+    "os_release": {
+      "type": "object",
+      "description": "Every key of the os-release file, present when the os field was collected.",
+      "additionalProperties": { "type": "string" }
+    },
+    "errors": {
+      "type": "array",
+      "items": {
+        "type": "object",
+        "required": ["field", "message"],
+        "properties": {
+          "field": { "type": "string" },
+          "message": { "type": "string" }
+        }
+      }
+    }
+  }
+}
+"#;
+
+/// Appends `s` as a quoted JSON string.
+pub fn write_str(out: &mut String, s: &str) {
+  out.push('"');
+  for c in s.chars() {
+    match c {
+      '"' => out.push_str("\\\""),
+      '\\' => out.push_str("\\\\"),
+      '\n' => out.push_str("\\n"),
+      '\r' => out.push_str("\\r"),
+      '\t' => out.push_str("\\t"),
+      c if c.is_control() => {
+        write!(out, "\\u{:04x}", u32::from(c)).unwrap();
+      },
+      c => out.push(c),
+    }
+  }
+  out.push('"');
+}
+
+/// An object member: its key, and a closure that writes its value.
+type Member<'a> = (&'a str, &'a dyn Fn(&mut String));
+
+/// Appends a JSON object made of the given members.
+fn write_object<const N: usize>(out: &mut String, members: [Member; N]) {
+  out.push('{');
+  for (i, (key, value)) in members.into_iter().enumerate() {
+    if i > 0 {
+      out.push(',');
+    }
+    write_str(out, key);
+    out.push(':');
+    value(out);
+  }
+  out.push('}');
+}
+
+#[cfg(any(feature = "uptime", feature = "memory", feature = "storage"))]
+fn write_u64(out: &mut String, n: u64) {
+  write!(out, "{n}").unwrap();
+}
+
+#[cfg(feature = "desktop")]
+fn write_opt_str(out: &mut String, s: Option<&str>) {
+  match s {
+    Some(s) => write_str(out, s),
+    None => out.push_str("null"),
+  }
+}
+
+/// Appends the JSON representation of a value. Returns `false` for values
+/// that carry no data and are left out of the output.
+fn write_value(out: &mut String, value: &Value) -> bool {
+  match value {
+    Value::User(info) => {
+      write_object(out, [
+        ("username", &|out| write_str(out, &info.username)),
+        ("hostname", &|out| write_str(out, &info.hostname)),
+      ]);
+    },
+    Value::Kernel(info) => {
+      write_object(out, [
+        ("sysname", &|out| write_str(out, &info.sysname)),
+        ("release", &|out| write_str(out, &info.release)),
+        ("machine", &|out| write_str(out, &info.machine)),
+      ]);
+    },
+    #[cfg(feature = "desktop")]
+    Value::Desktop(info) => {
+      write_object(out, [
+        ("desktop", &|out| {
+          write_opt_str(out, info.desktop.as_deref());
+        }),
+        ("session_type", &|out| {
+          write_opt_str(out, info.session_type.as_deref());
+        }),
+      ]);
+    },
+    #[cfg(feature = "uptime")]
+    Value::Uptime(uptime) => {
+      write_object(out, [("seconds", &|out| write_u64(out, uptime.seconds))]);
+    },
+    #[cfg(feature = "memory")]
+    Value::Memory(info) => {
+      write_object(out, [
+        ("total", &|out| write_u64(out, info.total)),
+        ("available", &|out| write_u64(out, info.available)),
+        ("used", &|out| write_u64(out, info.used())),
+      ]);
+    },
+    #[cfg(feature = "storage")]
+    Value::Disk(usage) => {
+      write_object(out, [
+        ("mount_point", &|out| write_str(out, &usage.mount_point)),
+        ("total", &|out| write_u64(out, usage.total)),
+        ("free", &|out| write_u64(out, usage.free)),
+        ("available", &|out| write_u64(out, usage.available)),
+        ("used", &|out| write_u64(out, usage.used())),
+      ]);
+    },
+    #[cfg(feature = "colors")]
+    Value::Palette => return false,
+    Value::Os(release) => write_str(out, release.pretty_name()),
+    Value::Text(text) => write_str(out, text),
+  }
+  true
+}
+
+/// Serializes the collected fields as a single JSON object, followed by a
+/// newline.
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn to_json(fields: &Fields) -> String {
+  let mut out = String::with_capacity(512);
+
+  write!(out, "{{\"version\":{SCHEMA_VERSION},\"fields\":{{").unwrap();
+  let mut first = true;
+  for entry in &fields.entries {
+    let start = out.len();
+    if !first {
+      out.push(',');
+    }
+    write_str(&mut out, entry.field.name());
+    out.push(':');
+    let written = if let Some(value) = &entry.value {
+      write_value(&mut out, value)
+    } else {
+      out.push_str("null");
+      true
+    };
+    if written {
+      first = false;
+    } else {
+      out.truncate(start);
+    }
+  }
+
+  out.push('}');
+
+  if let Some(release) = fields.os_release() {
+    out.push_str(",\"os_release\":{");
+    for (i, (key, value)) in release.iter().enumerate() {
+      if i > 0 {
+        out.push(',');
+      }
+      write_str(&mut out, key);
+      out.push(':');
+      write_str(&mut out, value);
+    }
+    out.push('}');
+  }
+
+  out.push_str(",\"errors\":[");
+  for (i, error) in fields.errors.iter().enumerate() {
+    if i > 0 {
+      out.push(',');
+    }
+    write_object(&mut out, [
+      ("field", &|out| write_str(out, error.field)),
+      ("message", &|out| write_str(out, &error.source.to_string())),
+    ]);
+  }
+  out.push_str("]}\n");
+
+  out
+}
+
+/// A parsed JSON value. Objects keep their members in document order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Json {
+  Null,
+  Bool(bool),
+  Number(u64),
+  String(String),
+  Array(Vec<Self>),
+  Object(Vec<(String, Self)>),
+}
+
+impl Json {
+  /// Looks up `key` if this is an object.
+  #[must_use]
+  pub fn get(&self, key: &str) -> Option<&Self> {
+    match self {
+      Self::Object(members) => {
+        members.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+      },
+      _ => None,
+    }
+  }
+
+  #[must_use]
+  pub fn as_str(&self) -> Option<&str> {
+    match self {
+      Self::String(s) => Some(s),
+      _ => None,
+    }
+  }
+
+  #[must_use]
+  pub const fn as_u64(&self) -> Option<u64> {
+    match self {
+      Self::Number(n) => Some(*n),
+      _ => None,
+    }
+  }
+}
+
+/// Why a document could not be parsed, and the byte offset it happened at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+  pub offset:  usize,
+  pub message: &'static str,
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} at byte {}", self.message, self.offset)
+  }
+}
+
+impl Error for ParseError {}
+
+/// Nesting limit, so that hostile input cannot overflow the stack.
+const MAX_DEPTH: usize = 32;
+
+struct Parser<'a> {
+  source: &'a str,
+  pos:    usize,
+}
+
+impl Parser<'_> {
+  const fn error<T>(&self, message: &'static str) -> Result<T, ParseError> {
+    Err(ParseError {
+      offset: self.pos,
+      message,
+    })
+  }
+
+  fn peek(&self) -> Option<u8> {
+    self.source.as_bytes().get(self.pos).copied()
+  }
+
+  fn skip_whitespace(&mut self) {
+    while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+      self.pos += 1;
+    }
+  }
+
+  fn expect(
+    &mut self,
+    byte: u8,
+    message: &'static str,
+  ) -> Result<(), ParseError> {
+    self.skip_whitespace();
+    if self.peek() != Some(byte) {
+      return self.error(message);
+    }
+    self.pos += 1;
+    Ok(())
+  }
+
+  fn literal(&mut self, word: &str, value: Json) -> Result<Json, ParseError> {
+    if !self.source[self.pos..].starts_with(word) {
+      return self.error("invalid literal");
+    }
+    self.pos += word.len();
+    Ok(value)
+  }
+
+  fn value(&mut self, depth: usize) -> Result<Json, ParseError> {
+    if depth > MAX_DEPTH {
+      return self.error("nesting too deep");
+    }
+    self.skip_whitespace();
+    match self.peek() {
+      Some(b'n') => self.literal("null", Json::Null),
+      Some(b't') => self.literal("true", Json::Bool(true)),
+      Some(b'f') => self.literal("false", Json::Bool(false)),
+      Some(b'"') => self.string().map(Json::String),
+      Some(b'0'..=b'9') => self.number(),
+      Some(b'[') => self.array(depth),
+      Some(b'{') => self.object(depth),
+      Some(_) => self.error("expected a value"),
+      None => self.error("unexpected end of input"),
+    }
+  }
+
+  fn number(&mut self) -> Result<Json, ParseError> {
+    let mut n = 0u64;
+    while let Some(byte @ b'0'..=b'9') = self.peek() {
+      n = n
+        .checked_mul(10)
+        .and_then(|n| n.checked_add(u64::from(byte - b'0')))
+        .map_or_else(|| self.error("number out of range"), Ok)?;
+      self.pos += 1;
+    }
+    if matches!(self.peek(), Some(b'.' | b'e' | b'E')) {
+      return self.error("only non-negative integers are supported");
+    }
+    Ok(Json::Number(n))
+  }
+
+  fn hex4(&mut self) -> Result<u32, ParseError> {
+    let digits = self
+      .source
+      .get(self.pos..self.pos + 4)
+      .filter(|s| s.bytes().all(|b| b.is_ascii_hexdigit()))
+      .map_or_else(|| self.error("invalid unicode escape"), Ok)?;
+    self.pos += 4;
+    Ok(u32::from_str_radix(digits, 16).unwrap_or_default())
+  }
+
+  fn string(&mut self) -> Result<String, ParseError> {
+    // Skip the opening quote
+    self.pos += 1;
+    let mut out = String::new();
+    let mut start = self.pos;
+
+    loop {
+      match self.peek() {
+        None => return self.error("unterminated string"),
+        Some(b'"') => {
+          out.push_str(&self.source[start..self.pos]);
+          self.pos += 1;
+          return Ok(out);
+        },
+        Some(b'\\') => {
+          out.push_str(&self.source[start..self.pos]);
+          self.pos += 1;
+          let escaped = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+              self.pos += 1;
+              let mut code = self.hex4()?;
+              // A high surrogate must be followed by an escaped low one
+              if (0xD800..0xDC00).contains(&code) {
+                if !self.source[self.pos..].starts_with("\\u") {
+                  return self.error("unpaired surrogate");
+                }
+                self.pos += 2;
+                let low = self.hex4()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                  return self.error("unpaired surrogate");
+                }
+                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+              }
+              let c = char::from_u32(code)
+                .map_or_else(|| self.error("unpaired surrogate"), Ok)?;
+              out.push(c);
+              start = self.pos;
+              continue;
+            },
+            _ => return self.error("invalid escape"),
+          };
+          out.push(escaped);
+          self.pos += 1;
+          start = self.pos;
+        },
+        Some(byte) if byte < 0x20 => {
+          return self.error("control character in string");
+        },
+        Some(_) => self.pos += 1,
+      }
+    }
+  }
+
+  fn array(&mut self, depth: usize) -> Result<Json, ParseError> {
+    self.pos += 1;
+    let mut items = Vec::new();
+    self.skip_whitespace();
+    if self.peek() == Some(b']') {
+      self.pos += 1;
+      return Ok(Json::Array(items));
+    }
+    loop {
+      items.push(self.value(depth + 1)?);
+      self.skip_whitespace();
+      match self.peek() {
+        Some(b',') => self.pos += 1,
+        Some(b']') => {
+          self.pos += 1;
+          return Ok(Json::Array(items));
+        },
+        _ => return self.error("expected ',' or ']'"),
+      }
+    }
+  }
+
+  fn object(&mut self, depth: usize) -> Result<Json, ParseError> {
+    self.pos += 1;
+    let mut members = Vec::new();
+    self.skip_whitespace();
+    if self.peek() == Some(b'}') {
+      self.pos += 1;
+      return Ok(Json::Object(members));
+    }
+    loop {
+      self.skip_whitespace();
+      if self.peek() != Some(b'"') {
+        return self.error("expected a string key");
+      }
+      let key = self.string()?;
+      self.expect(b':', "expected ':'")?;
+      members.push((key, self.value(depth + 1)?));
+      self.skip_whitespace();
+      match self.peek() {
+        Some(b',') => self.pos += 1,
+        Some(b'}') => {
+          self.pos += 1;
+          return Ok(Json::Object(members));
+        },
+        _ => return self.error("expected ',' or '}'"),
+      }
+    }
+  }
+}
+
+/// Parses a complete JSON document.
+///
+/// # Errors
+///
+/// Returns an error if `source` is not valid JSON, contains numbers other than
+/// non-negative integers, or has trailing data after the document.
+pub fn parse(source: &str) -> Result<Json, ParseError> {
+  let mut parser = Parser { source, pos: 0 };
+  let value = parser.value(0)?;
+  parser.skip_whitespace();
+  if parser.pos != source.len() {
+    return parser.error("trailing data");
+  }
+  Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_values() {
+    assert_eq!(
+      parse(r#" {"a": [1, true, null], "b": {}, "c": "x"} "#),
+      Ok(Json::Object(vec![
+        (
+          "a".to_owned(),
+          Json::Array(vec![Json::Number(1), Json::Bool(true), Json::Null]),
+        ),
+        ("b".to_owned(), Json::Object(Vec::new())),
+        ("c".to_owned(), Json::String("x".to_owned())),
+      ]))
+    );
+    assert_eq!(parse("18446744073709551615"), Ok(Json::Number(u64::MAX)));
+    assert_eq!(parse("[]"), Ok(Json::Array(Vec::new())));
+  }
+
+  #[test]
+  fn parses_escapes() {
+    let parsed = parse(r#""\"\\\/\b\f\n\r\té🦀""#);
+    assert_eq!(
+      parsed.unwrap().as_str(),
+      Some("\"\\/\u{8}\u{c}\n\r\té\u{1F980}")
+    );
+  }
+
+  #[test]
+  fn rejects_malformed_documents() {
+    for (source, offset, message) in [
+      ("", 0, "unexpected end of input"),
+      ("{} {}", 3, "trailing data"),
+      ("-1", 0, "expected a value"),
+      ("1.5", 1, "only non-negative integers are supported"),
+      ("18446744073709551616", 19, "number out of range"),
+      ("[1 2]", 3, "expected ',' or ']'"),
+      ("{1: 2}", 1, "expected a string key"),
+      (r#"{"a" 1}"#, 5, "expected ':'"),
+      (r#""abc"#, 4, "unterminated string"),
+      ("\"a\nb\"", 2, "control character in string"),
+      (r#""\x""#, 2, "invalid escape"),
+      (r#""\ud83e""#, 7, "unpaired surrogate"),
+      (r#""\u12""#, 3, "invalid unicode escape"),
+      ("nul", 0, "invalid literal"),
+    ] {
+      assert_eq!(
+        parse(source),
+        Err(ParseError { offset, message }),
+        "{source:?}"
+      );
+    }
+  }
+
+  #[test]
+  fn limits_nesting() {
+    let nested = |depth| "[".repeat(depth) + &"]".repeat(depth);
+    assert!(parse(&nested(MAX_DEPTH + 1)).is_ok());
+    assert_eq!(
+      parse(&nested(MAX_DEPTH + 2)).unwrap_err().message,
+      "nesting too deep"
+    );
+  }
+
+  #[test]
+  fn writes_strings_that_parse_back() {
+    let original = "quote \" backslash \\ newline \n tab \t bell \u{7} é";
+    let mut out = String::new();
+    write_str(&mut out, original);
+    assert_eq!(
+      out,
+      r#""quote \" backslash \\ newline \n tab \t bell \u0007 é""#
+    );
+    assert_eq!(parse(&out).unwrap().as_str(), Some(original));
+  }
+}
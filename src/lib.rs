@@ -1,17 +1,59 @@
+pub mod cli;
+#[macro_use]
 pub mod colors;
-pub mod desktop;
-pub mod dots;
+pub mod config;
+#[cfg(feature = "desktop")] pub mod desktop;
+#[cfg(feature = "colors")] pub mod dots;
+pub mod error;
+pub mod field;
+pub mod graphics;
+pub mod image;
+pub mod json;
+pub mod logo;
 pub mod release;
+pub mod render;
+pub mod shell;
+pub mod snapshot;
 pub mod syscall;
 pub mod system;
-pub mod uptime;
+pub mod terminal;
+pub mod terminfo;
+pub mod theme;
+#[cfg(feature = "uptime")] pub mod uptime;
+pub mod width;
 
-use std::{io, mem::MaybeUninit};
+use std::{borrow::Cow, ffi::CStr, io, mem::MaybeUninit};
+
+use crate::{
+  error::FieldError,
+  field::{Context, Field, Value},
+  release::OsRelease,
+};
 
 #[inline]
 #[cold]
-pub const fn unknown() -> &'static str { "Unknown" }
+#[must_use]
+pub const fn unknown() -> &'static str {
+  "Unknown"
+}
+
+/// A non-empty environment variable, if it is valid UTF-8.
+pub(crate) fn getenv(name: &CStr) -> Option<&'static str> {
+  unsafe {
+    let ptr = libc::getenv(name.as_ptr());
+    if ptr.is_null() {
+      return None;
+    }
+    CStr::from_ptr(ptr).to_str().ok().filter(|s| !s.is_empty())
+  }
+}
 
+/// Returns the calling thread's last OS error as an `Err`.
+///
+/// # Errors
+///
+/// Always returns an error; this is a shorthand for bailing out after a failed
+/// syscall.
 #[inline]
 #[cold]
 pub fn last_os_error<T>() -> io::Result<T> {
@@ -55,3 +97,226 @@ impl UtsName {
     unsafe { std::ffi::CStr::from_ptr(self.0.machine.as_ptr()) }
   }
 }
+
+/// The directory every system file is resolved against.
+///
+/// The default root is `/`, i.e. the running system. Pointing it elsewhere
+/// lets microfetch describe a chroot, an installer target mounted under
+/// `/mnt`, or a directory of captured `/etc` and `/proc` fixtures. Only file
+/// reads and `statvfs` honor the root; `uname` and the environment always
+/// describe the running host.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Root(Option<String>);
+
+impl Root {
+  /// Creates a root at `dir`. An empty path or `/` is the running system.
+  #[must_use]
+  pub fn new(dir: &str) -> Self {
+    let dir = dir.trim_end_matches('/');
+    if dir.is_empty() {
+      Self(None)
+    } else {
+      Self(Some(dir.to_owned()))
+    }
+  }
+
+  /// Whether this is the running system rather than an alternate root.
+  #[must_use]
+  pub const fn is_host(&self) -> bool {
+    self.0.is_none()
+  }
+
+  /// Resolves the absolute `path` against this root. Symbolic links are
+  /// followed as if the root were `/`, so that e.g. an absolute link from
+  /// `/etc/os-release` to `/etc/static/os-release` stays within the root.
+  #[must_use]
+  pub fn path<'a>(&self, path: &'a str) -> Cow<'a, str> {
+    self
+      .0
+      .as_ref()
+      .map_or(Cow::Borrowed(path), |dir| Cow::Owned(resolve(dir, path)))
+  }
+}
+
+/// Resolves `path` within `dir`, following symbolic links as if `dir` were
+/// `/`, and never climbing above it. Past the kernel's limit of 40 links, the
+/// rest are left unresolved.
+fn resolve(dir: &str, path: &str) -> String {
+  const MAX_LINKS: usize = 40;
+
+  let mut resolved = dir.to_owned();
+  // Components still to resolve, the next one last
+  let mut pending: Vec<String> =
+    path.split('/').rev().map(str::to_owned).collect();
+  let mut links = 0;
+
+  while let Some(component) = pending.pop() {
+    match component.as_str() {
+      "" | "." => continue,
+      ".." => {
+        let parent = resolved.rfind('/').unwrap_or(0).max(dir.len());
+        resolved.truncate(parent);
+        continue;
+      },
+      _ => {},
+    }
+
+    let parent = resolved.len();
+    resolved.push('/');
+    resolved.push_str(&component);
+    if links == MAX_LINKS {
+      continue;
+    }
+    if let Some(target) = std::fs::read_link(&resolved)
+      .ok()
+      .and_then(|target| target.into_os_string().into_string().ok())
+    {
+      links += 1;
+      let base = if target.starts_with('/') {
+        dir.len()
+      } else {
+        parent
+      };
+      resolved.truncate(base);
+      pending.extend(target.split('/').rev().map(str::to_owned));
+    }
+  }
+
+  if resolved.len() == dir.len() {
+    resolved.push('/');
+  }
+  resolved
+}
+
+/// A field together with its collected value, or `None` if collecting it
+/// failed.
+pub struct Entry {
+  pub field: &'static dyn Field,
+  pub value: Option<Value>,
+}
+
+/// Everything collected for a single fetch, as plain data. Turning this into
+/// the colored output is the job of the [`render`] module.
+///
+/// Fields that could not be collected have no value, and the reason is
+/// recorded in [`Fields::errors`]. A single failing field never prevents the
+/// others from being collected.
+pub struct Fields {
+  pub entries: Vec<Entry>,
+  pub errors:  Vec<FieldError>,
+  /// The os-release of the described system when it is known without the
+  /// `os` field, e.g. from a snapshot, so that the logo can still be picked.
+  pub release: Option<OsRelease>,
+}
+
+impl Fields {
+  /// Collects the given fields from the system at `root`, in order.
+  #[must_use]
+  #[cfg_attr(feature = "hotpath", hotpath::measure)]
+  pub fn collect(fields: &[&'static dyn Field], root: Root) -> Self {
+    let ctx = Context::new(root);
+    let mut errors = Vec::new();
+
+    let entries = fields
+      .iter()
+      .map(|&field| {
+        let value = field
+          .collect(&ctx)
+          .map_err(|err| errors.push(FieldError::new(field.name(), err)))
+          .ok();
+        Entry { field, value }
+      })
+      .collect();
+
+    Self {
+      entries,
+      errors,
+      release: None,
+    }
+  }
+
+  /// The os-release of the described system: the value of the `os` field,
+  /// or else [`Fields::release`].
+  #[must_use]
+  pub fn os_release(&self) -> Option<&OsRelease> {
+    self
+      .entries
+      .iter()
+      .find_map(|entry| {
+        match &entry.value {
+          Some(Value::Os(release)) => Some(release),
+          _ => None,
+        }
+      })
+      .or(self.release.as_ref())
+  }
+}
+
+/// A fresh directory for a test, removed again when dropped, even if the test
+/// panics.
+#[cfg(test)]
+pub(crate) struct TempDir(std::path::PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+  /// Creates a directory that no other test, or run of the tests, shares.
+  pub fn new(name: &str) -> Self {
+    static COUNT: std::sync::atomic::AtomicUsize =
+      std::sync::atomic::AtomicUsize::new(0);
+    let count = COUNT.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let dir = std::env::temp_dir()
+      .join(format!("microfetch-{name}-{}-{count}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    Self(dir)
+  }
+
+  /// `path` within the directory.
+  pub fn join(&self, path: &str) -> std::path::PathBuf {
+    self.0.join(path)
+  }
+
+  /// The directory as a string, e.g. for [`Root::new`].
+  pub fn as_str(&self) -> &str {
+    self.0.to_str().unwrap()
+  }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_dir_all(&self.0);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::os::unix::fs::symlink;
+
+  use super::*;
+
+  #[test]
+  fn root_follows_links_within_itself() {
+    let temp = TempDir::new("root");
+    std::fs::create_dir_all(temp.join("etc/static")).unwrap();
+    std::fs::create_dir_all(temp.join("usr/lib")).unwrap();
+    symlink("/etc/static/os-release", temp.join("etc/os-release")).unwrap();
+    symlink(
+      "../../usr/lib/os-release",
+      temp.join("etc/static/os-release"),
+    )
+    .unwrap();
+    symlink("../../../../..", temp.join("usr/lib/up")).unwrap();
+    symlink("loop", temp.join("etc/loop")).unwrap();
+
+    let root = Root::new(temp.as_str());
+    let dir = temp.as_str();
+    assert_eq!(
+      root.path("/etc/os-release"),
+      format!("{dir}/usr/lib/os-release")
+    );
+    assert_eq!(root.path("/usr/lib/up/etc"), format!("{dir}/etc"));
+    assert_eq!(root.path("/../proc/uptime"), format!("{dir}/proc/uptime"));
+    assert_eq!(root.path("/"), format!("{dir}/"));
+    assert_eq!(root.path("/etc/loop"), format!("{dir}/etc/loop"));
+  }
+}
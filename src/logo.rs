@@ -0,0 +1,380 @@
+//! Logo art drawn next to the fields. Lines may contain `${c1}` to `${c6}`
+//! placeholders that are replaced with colors when rendered: the theme's
+//! primary and secondary logo colors, then green, yellow, red and magenta
+//! from [`Colors`](crate::colors::Colors). Anything else, such as `${c7}`, is
+//! drawn as it is.
+//!
+//! Every logo in [`REGISTRY`] draws its main shape with `${c1}`, so that it
+//! can be recolored with the distribution's `ANSI_COLOR`.
+
+use std::io::{self, Write};
+
+use crate::{
+  colors::{Color, Colors},
+  config::baked,
+  release::OsRelease,
+  syscall::read_file_fast,
+  theme,
+  width::display_width,
+};
+
+pub struct Logo {
+  /// Name used to select the logo, matching an os-release `ID`.
+  pub name:  &'static str,
+  pub lines: &'static [&'static str],
+  /// A variant of only a few lines, for short terminals.
+  pub small: Option<&'static Self>,
+}
+
+impl Logo {
+  /// The small variant of the logo, or the logo itself if it has none.
+  #[must_use]
+  pub fn small(&self) -> &Self {
+    self.small.unwrap_or(self)
+  }
+
+  /// Width of the widest line in terminal columns.
+  #[must_use]
+  pub fn width(&self) -> usize {
+    self
+      .lines
+      .iter()
+      .map(|line| line_width(line))
+      .max()
+      .unwrap_or(0)
+  }
+}
+
+pub const NIXOS: Logo = Logo {
+  name:  "nixos",
+  lines: &[
+    "${c2}     ▟█▖    ${c1}▝█▙ ▗█▛",
+    "${c2}  ▗▄▄▟██▄▄▄▄▄${c1}▝█▙█▛  ${c2}▖",
+    "${c2}  ▀▀▀▀▀▀▀▀▀▀▀▘${c1}▝██  ${c2}▟█▖",
+    "${c1}     ▟█▛       ${c1}▝█▘${c2}▟█▛",
+    "${c1}▟█████▛          ${c2}▟█████▛",
+    "${c1}   ▟█▛${c2}▗█▖       ${c2}▟█▛",
+    "${c1}  ▝█▛  ${c2}██▖${c1}▗▄▄▄▄▄▄▄▄▄▄▄",
+    "${c1}   ▝  ${c2}▟█▜█▖${c1}▀▀▀▀▀██▛▀▀▘",
+    "${c2}     ▟█▘ ▜█▖    ${c1}▝█▛",
+  ],
+  small: Some(&NIXOS_SMALL),
+};
+
+pub const ALPINE: Logo = Logo {
+  name:  "alpine",
+  lines: &[
+    "${c1}       /\\ /\\",
+    "${c1}      /  \\  \\",
+    "${c1}     /    \\  \\",
+    "${c1}    /  /\\  \\  \\",
+    "${c1}   /  /  \\  \\  \\",
+    "${c1}  /  /    \\  \\  \\",
+    "${c1} /  /      \\  \\  \\",
+  ],
+  small: Some(&ALPINE_SMALL),
+};
+
+pub const ARCH: Logo = Logo {
+  name:  "arch",
+  lines: &[
+    "${c1}       /\\",
+    "${c1}      /  \\",
+    "${c1}     /\\   \\",
+    "${c1}    /      \\",
+    "${c1}   /   ,,   \\",
+    "${c1}  /   |  |  -\\",
+    "${c1} /_-''    ''-_\\",
+  ],
+  small: Some(&ARCH_SMALL),
+};
+
+pub const DEBIAN: Logo = Logo {
+  name:  "debian",
+  lines: &[
+    "${c1}    _,.--.,_",
+    "${c1}  ,'        `.",
+    "${c1} /   ,-\"-.    \\",
+    "${c1}|   /  ,_ \\   |",
+    "${c1}|   \\  `-' ,  ;",
+    "${c1} `.  `-.__.-'",
+    "${c1}   `-._",
+  ],
+  small: Some(&DEBIAN_SMALL),
+};
+
+pub const TUX: Logo = Logo {
+  name:  "tux",
+  lines: &[
+    "${c1}    .--.",
+    "${c1}   |o${c4}_${c1}o |",
+    "${c1}   |${c4}:_/${c1} |",
+    "${c1}  //   \\ \\",
+    "${c1} (|     | )",
+    "${c1}/'\\_   _/`\\",
+    "${c1}\\___)=(___/",
+  ],
+  small: Some(&TUX_SMALL),
+};
+
+pub const NIXOS_SMALL: Logo = Logo {
+  name:  "nixos",
+  lines: &[
+    "${c1}  \\\\  ${c2}\\\\ //",
+    "${c1} ==\\\\__${c2}\\\\/ //",
+    "${c2}   //   ${c1}\\\\//",
+    "${c2}==//     ${c1}//==",
+    "${c2} //\\\\___${c1}//",
+  ],
+  small: None,
+};
+
+pub const ALPINE_SMALL: Logo = Logo {
+  name:  "alpine",
+  lines: &[
+    "${c1}   /\\ /\\",
+    "${c1}  // \\  \\",
+    "${c1} //   \\  \\",
+    "${c1}///    \\  \\",
+    "${c1}//      \\  \\",
+  ],
+  small: None,
+};
+
+pub const ARCH_SMALL: Logo = Logo {
+  name:  "arch",
+  lines: &[
+    "${c1}    /\\",
+    "${c1}   /  \\",
+    "${c1}  /    \\",
+    "${c1} /  __  \\",
+    "${c1}/-''  ''-\\",
+  ],
+  small: None,
+};
+
+pub const DEBIAN_SMALL: Logo = Logo {
+  name:  "debian",
+  lines: &[
+    "${c1}  ,---.",
+    "${c1} /  _  \\",
+    "${c1}|  ( `  ;",
+    "${c1} \\  `-'",
+    "${c1}  `-._",
+  ],
+  small: None,
+};
+
+pub const TUX_SMALL: Logo = Logo {
+  name:  "tux",
+  lines: &[
+    "${c1}  .--.",
+    "${c1} |o${c4}_${c1}o |",
+    "${c1} |${c4}:_/${c1} |",
+    "${c1}//___\\ \\",
+    "${c1}(_)  (_)",
+  ],
+  small: None,
+};
+
+/// Every built-in logo, by name.
+pub static REGISTRY: &[&Logo] = &[&ALPINE, &ARCH, &DEBIAN, &NIXOS, &TUX];
+
+/// The logo drawn when the operating system is not known: the one baked in
+/// with `MICROFETCH_CONFIG`, if any, and Tux otherwise.
+pub const DEFAULT: &Logo = match &baked::LOGO {
+  Some(logo) => logo,
+  None => &TUX,
+};
+
+/// Looks up a built-in logo by name.
+#[must_use]
+pub fn find(name: &str) -> Option<&'static Logo> {
+  REGISTRY.iter().copied().find(|logo| logo.name == name)
+}
+
+/// Picks the logo for an operating system: the one matching its `ID`, or else
+/// the first match from `ID_LIKE`, and [`DEFAULT`] when nothing matches. It is
+/// returned together with the distribution's `ANSI_COLOR`, if any, to draw it
+/// in, except for a logo baked in with `MICROFETCH_CONFIG`, which keeps its own
+/// colors.
+#[must_use]
+pub fn for_release(release: &OsRelease) -> (&'static Logo, Option<Color>) {
+  let logo = std::iter::once(release.id())
+    .chain(release.id_like())
+    .find_map(find);
+  match (logo, &baked::LOGO) {
+    (None, Some(baked)) => (baked, None),
+    (logo, _) => (logo.unwrap_or(&TUX), ansi_color(release)),
+  }
+}
+
+/// The color of the `ANSI_COLOR` of an operating system. Only its color is
+/// kept, so that it is drawn like any other color, and an os-release file
+/// cannot smuggle arbitrary escape sequences into the output.
+fn ansi_color(release: &OsRelease) -> Option<Color> {
+  release.get("ANSI_COLOR").and_then(Color::from_sgr)
+}
+
+/// The index `N` of the `${cN}` placeholder that `line` starts with, if any.
+fn placeholder_index(line: &str) -> Option<u8> {
+  match *line.as_bytes() {
+    [b'$', b'{', b'c', index @ b'1'..=b'6', b'}', ..] => Some(index),
+    _ => None,
+  }
+}
+
+/// Width of a logo line in terminal columns, ignoring `${cN}` placeholders.
+#[must_use]
+pub fn line_width(line: &str) -> usize {
+  let mut width = 0;
+  let mut rest = line;
+  while let Some(start) = rest.find("${c") {
+    width += display_width(&rest[..start]);
+    rest = &rest[start..];
+    if placeholder_index(rest).is_some() {
+      rest = &rest[5..];
+    } else {
+      width += 3;
+      rest = &rest[3..];
+    }
+  }
+  width + display_width(rest)
+}
+
+/// Largest accepted logo file, in bytes.
+const MAX_LOGO_SIZE: usize = 8192;
+
+/// Reads a logo from a text file with `${cN}` color placeholders. Trailing
+/// whitespace and blank lines are dropped.
+///
+/// The logo is leaked: it is drawn once and needed until the process exits
+/// anyway, and this lets it be used like the built-in ones.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, is larger than 8 KiB or is not
+/// valid UTF-8.
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn load(path: &str) -> Result<&'static Logo, io::Error> {
+  // One byte more than allowed, to tell a file of exactly the limit apart
+  let mut buffer = [0u8; MAX_LOGO_SIZE + 1];
+  let bytes_read = read_file_fast(path, &mut buffer)?;
+  if bytes_read > MAX_LOGO_SIZE {
+    return Err(io::Error::new(
+      io::ErrorKind::FileTooLarge,
+      "logo is too large",
+    ));
+  }
+
+  let source = std::str::from_utf8(&buffer[..bytes_read]).map_err(|_| {
+    io::Error::new(io::ErrorKind::InvalidData, "logo is not valid UTF-8")
+  })?;
+  let source: &'static str = Box::leak(source.trim_end().into());
+  let lines = source.lines().map(str::trim_end).collect::<Vec<_>>().leak();
+
+  Ok(Box::leak(Box::new(Logo {
+    name: "custom",
+    lines,
+    small: None,
+  })))
+}
+
+/// The color of the `${cN}` placeholder with index `N`.
+fn placeholder_color(index: u8) -> Color {
+  match index {
+    b'1' => theme::current().logo_primary,
+    b'2' => theme::current().logo_secondary,
+    b'3' => Color::Green,
+    b'4' => Color::Yellow,
+    b'5' => Color::Red,
+    _ => Color::Magenta,
+  }
+}
+
+/// Writes a single logo line, expanding color placeholders. `primary`, if
+/// given, replaces the color of `${c1}`. Unknown placeholders are written out
+/// verbatim.
+///
+/// # Errors
+///
+/// Returns an error if writing to `out` fails.
+pub fn write_line(
+  out: &mut impl Write,
+  line: &str,
+  colors: &Colors,
+  primary: Option<Color>,
+) -> Result<(), io::Error> {
+  let mut rest = line;
+
+  while let Some(start) = rest.find("${c") {
+    out.write_all(&rest.as_bytes()[..start])?;
+    rest = &rest[start..];
+
+    if let Some(index) = placeholder_index(rest) {
+      let color = match (index, primary) {
+        (b'1', Some(primary)) => primary,
+        _ => placeholder_color(index),
+      };
+      out.write_all(colors.paint(color).as_bytes())?;
+      rest = &rest[5..];
+    } else {
+      out.write_all(b"${c")?;
+      rest = &rest[3..];
+    }
+  }
+
+  out.write_all(rest.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{TempDir, colors::COLORS};
+
+  #[test]
+  fn unknown_placeholders_are_text() {
+    for (line, width) in [
+      ("${c1}ab${c2}c", 3),
+      ("${c7}ab", 7),
+      ("${c1", 4),
+      ("${cc}${c6}", 5),
+      ("${c11}", 6),
+    ] {
+      assert_eq!(line_width(line), width, "{line:?}");
+
+      let mut out = Vec::new();
+      write_line(&mut out, line, &COLORS, None).unwrap();
+      let written = String::from_utf8(out).unwrap();
+      assert_eq!(display_width(&written), width, "{line:?}");
+    }
+  }
+
+  #[test]
+  fn picks_logo_by_id() {
+    let release = OsRelease::parse("ID=ubuntu\nID_LIKE=\"foo debian\"\n");
+    assert_eq!(for_release(&release).0.name, "debian");
+    let release = OsRelease::parse("ID=nixos\nANSI_COLOR=\"1;34\"\n");
+    let (logo, color) = for_release(&release);
+    assert_eq!((logo.name, color), ("nixos", Some(Color::Blue)));
+  }
+
+  #[test]
+  fn loads_logos_up_to_8_kib() {
+    let dir = TempDir::new("logo");
+    let path = dir.join("logo");
+    let path = path.to_str().unwrap();
+
+    // Seven bytes per line, and blank lines to make up exactly 8 KiB
+    let source = "${c1}x\n".repeat(MAX_LOGO_SIZE / 7) + "\n\n";
+    assert_eq!(source.len(), 8192);
+    std::fs::write(path, source).unwrap();
+    let logo = load(path).unwrap();
+    assert_eq!(logo.lines.len(), MAX_LOGO_SIZE / 7);
+    assert_eq!(logo.lines[0], "${c1}x");
+
+    std::fs::write(path, "x".repeat(MAX_LOGO_SIZE + 1)).unwrap();
+    let err = load(path).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::FileTooLarge);
+  }
+}
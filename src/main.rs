@@ -1,113 +1,147 @@
-mod colors;
-mod desktop;
-mod dots;
-mod release;
-mod syscall;
-mod system;
-mod uptime;
-
-use std::io::{self, Cursor, Write};
-
-pub use microfetch_lib::{UtsName, last_os_error, unknown};
-
-use crate::{
-  desktop::get_desktop_info,
-  dots::print_dots,
-  release::{get_os_pretty_name, get_system_info},
-  system::{
-    get_memory_usage,
-    get_root_disk_usage,
-    get_shell,
-    get_username_and_hostname,
-  },
-  uptime::get_current,
+use std::{
+  fmt,
+  io::{self, Write},
 };
 
-#[cfg_attr(feature = "hotpath", hotpath::main)]
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-  if Some("--version") == std::env::args().nth(1).as_deref() {
-    println!("Microfetch {}", env!("CARGO_PKG_VERSION"));
-  } else {
-    let utsname = UtsName::uname()?;
-    let fields = Fields {
-      user_info:      get_username_and_hostname(&utsname),
-      os_name:        get_os_pretty_name()?,
-      kernel_version: get_system_info(&utsname),
-      shell:          get_shell(),
-      desktop:        get_desktop_info(),
-      uptime:         get_current()?,
-      memory_usage:   get_memory_usage()?,
-      storage:        get_root_disk_usage()?,
-      dots:           print_dots(),
-    };
-    print_system_info(&fields)?;
-  }
-
-  Ok(())
-}
+use microfetch_lib::{
+  Fields,
+  cli::{self, Command, Format, LogoChoice, Source},
+  colors::{Background, IS_NO_COLOR, set_background, set_color_choice},
+  config::Config,
+  graphics::{Graphic, Protocol},
+  image,
+  json,
+  logo,
+  release::read_os_release,
+  render::{self, print_system_info},
+  shell,
+  snapshot,
+  terminal,
+  theme,
+};
 
-// Struct to hold all the fields we need in order to print the fetch. This
-// helps avoid Clippy warnings about argument count, and makes it slightly
-// easier to pass data around. Though, it is not like we really need to.
-struct Fields {
-  user_info:      String,
-  os_name:        String,
-  kernel_version: String,
-  shell:          String,
-  uptime:         String,
-  desktop:        String,
-  memory_usage:   String,
-  storage:        String,
-  dots:           &'static str,
+/// Reports a fatal error and exits.
+fn fail(err: impl fmt::Display) -> ! {
+  eprintln!("microfetch: {err}");
+  std::process::exit(1);
 }
 
-#[cfg_attr(feature = "hotpath", hotpath::measure)]
-fn print_system_info(
-  fields: &Fields,
-) -> Result<(), Box<dyn std::error::Error>> {
-  use crate::colors::COLORS;
-
-  let Fields {
-    user_info,
-    os_name,
-    kernel_version,
-    shell,
-    uptime,
-    desktop,
-    memory_usage,
-    storage,
-    dots,
-  } = fields;
+#[cfg_attr(feature = "hotpath", hotpath::main)]
+fn main() -> Result<(), io::Error> {
+  let args = match cli::parse(std::env::args().skip(1)) {
+    Ok(Command::Fetch(args)) => args,
+    Ok(Command::Help) => {
+      print!("{}", cli::help());
+      return Ok(());
+    },
+    Ok(Command::Version) => {
+      println!("Microfetch {}", env!("CARGO_PKG_VERSION"));
+      return Ok(());
+    },
+    Ok(Command::Schema) => {
+      print!("{}", json::SCHEMA);
+      return Ok(());
+    },
+    Err(err) => {
+      eprintln!("microfetch: {err}");
+      eprintln!("Try 'microfetch --help' for more information.");
+      std::process::exit(2);
+    },
+  };
 
-  let cyan = COLORS.cyan;
-  let blue = COLORS.blue;
-  let reset = COLORS.reset;
+  // The configuration is optional, so a broken one must not stop the fetch
+  let config = Config::load().unwrap_or_else(|err| {
+    eprintln!("microfetch: {err}, ignoring it");
+    Config::baked().unwrap_or_default()
+  });
+  for warning in &config.warnings {
+    eprintln!("microfetch: {warning}");
+  }
 
-  let mut buf = [0u8; 2048];
-  let mut cursor = Cursor::new(&mut buf[..]);
+  set_color_choice(args.color);
+  // Only worth asking the terminal if its answer is used
+  set_background(args.background.unwrap_or_else(|| {
+    if *IS_NO_COLOR || args.format != Format::Pretty {
+      Background::Dark
+    } else {
+      terminal::background().map_or(Background::Dark, Background::from_rgb)
+    }
+  }));
+  theme::set(config.resolve_theme(args.theme));
+  let selected = args.fields(config.fields.as_deref());
+  let fields = match &args.source {
+    Source::System => Fields::collect(&selected, args.root.clone()),
+    Source::Snapshot(path) => {
+      snapshot::load(path, &selected).unwrap_or_else(|err| fail(err))
+    },
+    Source::Mock => {
+      snapshot::parse(snapshot::MOCK, &selected).unwrap_or_else(|err| fail(err))
+    },
+  };
 
-  write!(
-    cursor,
-    "
-    {cyan}     ▟█▖    {blue}▝█▙ ▗█▛         {user_info} ~{reset}
-    {cyan}  ▗▄▄▟██▄▄▄▄▄{blue}▝█▙█▛  {cyan}▖       {cyan}  {blue}System{reset}        {os_name}
-    {cyan}  ▀▀▀▀▀▀▀▀▀▀▀▘{blue}▝██  {cyan}▟█▖      {cyan}  {blue}Kernel{reset}        {kernel_version}
-    {blue}     ▟█▛       {blue}▝█▘{cyan}▟█▛       {cyan}  {blue}Shell{reset}         {shell}
-    {blue}▟█████▛          {cyan}▟█████▛    {cyan}  {blue}Uptime{reset}        {uptime}
-    {blue}   ▟█▛{cyan}▗█▖       {cyan}▟█▛         {cyan}  {blue}Desktop{reset}       {desktop}
-    {blue}  ▝█▛  {cyan}██▖{blue}▗▄▄▄▄▄▄▄▄▄▄▄      {cyan}󰍛  {blue}Memory{reset}        {memory_usage}
-    {blue}   ▝  {cyan}▟█▜█▖{blue}▀▀▀▀▀██▛▀▀▘      {cyan}󱥎  {blue}Storage (/){reset}   {storage}
-    {cyan}     ▟█▘ ▜█▖    {blue}▝█▛         {cyan}  {blue}Colors{reset}        {dots}\n\n"
-  )?;
+  if let Some(path) = &args.save {
+    snapshot::save(path, &fields).unwrap_or_else(|err| fail(err));
+  }
 
-  let len = cursor.position() as usize;
-  // Direct syscall to avoid stdout buffering allocation
-  let written = unsafe { libc::write(libc::STDOUT_FILENO, buf.as_ptr().cast(), len) };
-  if written < 0 {
-    return Err(io::Error::last_os_error().into());
+  match args.format {
+    Format::Pretty => {
+      let graphic;
+      let mut options = render::Options {
+        styles: &config.styles,
+        columns: terminal::columns(),
+        lines: terminal::lines(),
+        layout: args.layout,
+        small: args.small,
+        ..Default::default()
+      };
+      let release;
+      match &args.logo {
+        LogoChoice::Auto => {
+          // Without the os field, the running system is asked separately
+          release = fields.os_release().cloned().or_else(|| {
+            (args.source == Source::System)
+              .then(|| read_os_release(&args.root).ok())
+              .flatten()
+          });
+          if let Some(release) = &release {
+            let (logo, color) = logo::for_release(release);
+            options.logo = Some(logo);
+            options.logo_color = color;
+          }
+        },
+        LogoChoice::Hidden => options.logo = None,
+        LogoChoice::Named(logo) => options.logo = Some(logo),
+        LogoChoice::File(path) => {
+          let logo = logo::load(path)
+            .unwrap_or_else(|err| fail(format_args!("{path}: {err}")));
+          options.logo = Some(logo);
+        },
+        LogoChoice::Image(path) => {
+          let image = image::load(path)
+            .unwrap_or_else(|err| fail(format_args!("{path}: {err}")));
+          let protocol = args.graphics.unwrap_or_else(Protocol::detect);
+          graphic = Graphic::new(&image, protocol);
+          options.logo = Some(graphic.placeholder());
+          options.image = Some(&graphic);
+        },
+      }
+      print_system_info(&fields, &options)?;
+    },
+    Format::Json => {
+      io::stdout().write_all(json::to_json(&fields).as_bytes())?;
+    },
+    Format::Shell => {
+      io::stdout().write_all(shell::to_shell(&fields).as_bytes())?;
+    },
   }
-  if written as usize != len {
-    return Err(io::Error::new(io::ErrorKind::WriteZero, "partial write to stdout").into());
+
+  // Failed fields are rendered as placeholders; only explain why when
+  // explicitly asked to.
+  if args.errors {
+    for error in &fields.errors {
+      eprintln!("microfetch: {error}");
+    }
   }
+
   Ok(())
 }
@@ -1,75 +1,320 @@
 use std::io;
 
-use crate::{syscall::read_file_fast, unknown, UtsName};
+use crate::{
+  Root,
+  UtsName,
+  field::{Context, Field, Value},
+  syscall::read_file_to_end,
+  unknown,
+};
+
+/// Kernel name, release and machine architecture as reported by `uname`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KernelInfo {
+  pub sysname: String,
+  pub release: String,
+  pub machine: String,
+}
 
 #[must_use]
 #[cfg_attr(feature = "hotpath", hotpath::measure)]
-pub fn get_system_info(utsname: &UtsName) -> String {
+pub fn get_system_info(utsname: &UtsName) -> KernelInfo {
   let sysname = utsname.sysname().to_str().unwrap_or_else(|_| unknown());
   let release = utsname.release().to_str().unwrap_or_else(|_| unknown());
   let machine = utsname.machine().to_str().unwrap_or_else(|_| unknown());
 
-  // Pre-allocate capacity: sysname + " " + release + " (" + machine + ")"
-  let capacity = sysname.len() + 1 + release.len() + 2 + machine.len() + 1;
-  let mut result = String::with_capacity(capacity);
-
-  result.push_str(sysname);
-  result.push(' ');
-  result.push_str(release);
-  result.push(' ');
-  result.push('(');
-  result.push_str(machine);
-  result.push(')');
-  result
+  KernelInfo {
+    sysname: sysname.to_owned(),
+    release: release.to_owned(),
+    machine: machine.to_owned(),
+  }
 }
 
-/// Gets the pretty name of the OS from `/etc/os-release`.
+/// The contents of an [os-release] file, which identifies the operating
+/// system.
 ///
-/// # Errors
+/// Every key is available through [`OsRelease::get`], e.g. `ID`, `ID_LIKE`,
+/// `VERSION_ID`, `BUILD_ID`, `VARIANT`, `ANSI_COLOR`, `LOGO` or `IMAGE_ID`.
+/// Keys with a default in the specification also have an accessor that
+/// applies it.
 ///
-/// Returns an error if `/etc/os-release` cannot be read.
-#[cfg_attr(feature = "hotpath", hotpath::measure)]
-pub fn get_os_pretty_name() -> Result<String, io::Error> {
-  // Fast byte-level scanning for PRETTY_NAME=
-  const PREFIX: &[u8] = b"PRETTY_NAME=";
+/// [os-release]: https://www.freedesktop.org/software/systemd/man/latest/os-release.html
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OsRelease {
+  entries: Vec<(String, String)>,
+}
 
-  let mut buffer = [0u8; 1024];
+impl OsRelease {
+  /// Parses the contents of an os-release file.
+  ///
+  /// Values follow shell quoting rules: they may be wrapped in single or
+  /// double quotes, and backslash escapes are honored outside single quotes.
+  /// Blank lines, comments and malformed lines are skipped, and a key that
+  /// appears twice keeps its last value.
+  #[must_use]
+  pub fn parse(source: &str) -> Self {
+    let mut release = Self::default();
 
-  // Use fast syscall-based file reading
-  let bytes_read = read_file_fast("/etc/os-release", &mut buffer)?;
-  let content = &buffer[..bytes_read];
+    for line in source.lines() {
+      let line = line.trim();
+      if line.is_empty() || line.starts_with('#') {
+        continue;
+      }
+      let Some((key, value)) = line.split_once('=') else {
+        continue;
+      };
+      if !is_valid_key(key) {
+        continue;
+      }
+      if let Some(value) = unquote(value) {
+        release.insert(key.to_owned(), value);
+      }
+    }
 
-  let mut offset = 0;
+    release
+  }
 
-  while offset < content.len() {
-    let remaining = &content[offset..];
+  /// Sets `key` to `value`, replacing any previous value.
+  pub fn insert(&mut self, key: String, value: String) {
+    match self.entries.iter_mut().find(|(k, _)| *k == key) {
+      Some(entry) => entry.1 = value,
+      None => self.entries.push((key, value)),
+    }
+  }
 
-    // Find newline or end
-    let line_end = remaining
+  /// Looks up the value of `key`, if set.
+  #[must_use]
+  pub fn get(&self, key: &str) -> Option<&str> {
+    self
+      .entries
       .iter()
-      .position(|&b| b == b'\n')
-      .unwrap_or(remaining.len());
-    let line = &remaining[..line_end];
-
-    if line.starts_with(PREFIX) {
-      let value = &line[PREFIX.len()..];
-
-      // Strip quotes if present
-      let trimmed = if value.len() >= 2
-        && value[0] == b'"'
-        && value[value.len() - 1] == b'"'
-      {
-        &value[1..value.len() - 1]
-      } else {
-        value
-      };
+      .find(|(k, _)| k == key)
+      .map(|(_, v)| v.as_str())
+  }
+
+  /// Every key and value, in file order.
+  pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.entries.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+  }
+
+  /// `NAME`, defaulting to `Linux`.
+  #[must_use]
+  pub fn name(&self) -> &str {
+    self.get("NAME").unwrap_or("Linux")
+  }
+
+  /// `PRETTY_NAME`, defaulting to `Linux`.
+  #[must_use]
+  pub fn pretty_name(&self) -> &str {
+    self.get("PRETTY_NAME").unwrap_or("Linux")
+  }
 
-      // Convert to String - should be valid UTF-8
-      return Ok(String::from_utf8_lossy(trimmed).into_owned());
+  /// `ID`, defaulting to `linux`.
+  #[must_use]
+  pub fn id(&self) -> &str {
+    self.get("ID").unwrap_or("linux")
+  }
+
+  /// The space-separated IDs listed in `ID_LIKE`, closest relative first.
+  pub fn id_like(&self) -> impl Iterator<Item = &str> {
+    self
+      .get("ID_LIKE")
+      .unwrap_or_default()
+      .split_ascii_whitespace()
+  }
+}
+
+fn is_valid_key(key: &str) -> bool {
+  let mut bytes = key.bytes();
+  bytes
+    .next()
+    .is_some_and(|b| b.is_ascii_alphabetic() || b == b'_')
+    && bytes.all(|b| b.is_ascii_alphanumeric() || b == b'_')
+}
+
+/// Expands a value written with shell quoting, or returns `None` if a quote is
+/// left unterminated.
+fn unquote(value: &str) -> Option<String> {
+  let mut out = String::with_capacity(value.len());
+  let mut chars = value.chars();
+
+  while let Some(c) = chars.next() {
+    match c {
+      '\'' => {
+        loop {
+          match chars.next()? {
+            '\'' => break,
+            c => out.push(c),
+          }
+        }
+      },
+      '"' => {
+        loop {
+          match chars.next()? {
+            '"' => break,
+            // Inside double quotes, only these characters can be escaped
+            '\\' => {
+              match chars.next()? {
+                c @ ('$' | '`' | '"' | '\\') => out.push(c),
+                c => {
+                  out.push('\\');
+                  out.push(c);
+                },
+              }
+            },
+            c => out.push(c),
+          }
+        }
+      },
+      '\\' => out.push(chars.next()?),
+      c => out.push(c),
     }
+  }
+
+  Some(out)
+}
+
+/// Reads the os-release file of the system at `root`, falling back to
+/// `/usr/lib/os-release` when `/etc/os-release` does not exist, as the
+/// specification requires.
+///
+/// # Errors
+///
+/// Returns an error if neither file can be read.
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn read_os_release(root: &Root) -> Result<OsRelease, io::Error> {
+  let mut buffer = Vec::with_capacity(1024);
+
+  match read_file_to_end(&root.path("/etc/os-release"), &mut buffer) {
+    Err(err) if err.kind() == io::ErrorKind::NotFound => {
+      read_file_to_end(&root.path("/usr/lib/os-release"), &mut buffer)?;
+    },
+    result => {
+      result?;
+    },
+  }
+
+  Ok(OsRelease::parse(&String::from_utf8_lossy(&buffer)))
+}
+
+/// Gets the pretty name of the OS from the os-release file under `root`.
+///
+/// # Errors
+///
+/// Returns an error if no os-release file can be read.
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn get_os_pretty_name(root: &Root) -> Result<String, io::Error> {
+  read_os_release(root).map(|release| release.pretty_name().to_owned())
+}
+
+pub struct OsField;
+
+impl Field for OsField {
+  fn name(&self) -> &'static str {
+    "os"
+  }
+
+  fn label(&self) -> &'static str {
+    "System"
+  }
+
+  fn icon(&self) -> &'static str {
+    "\u{F313}"
+  }
+
+  fn collect(&self, ctx: &Context) -> Result<Value, io::Error> {
+    read_os_release(ctx.root()).map(Value::Os)
+  }
+}
+
+pub struct KernelField;
+
+impl Field for KernelField {
+  fn name(&self) -> &'static str {
+    "kernel"
+  }
+
+  fn label(&self) -> &'static str {
+    "Kernel"
+  }
+
+  fn icon(&self) -> &'static str {
+    "\u{E712}"
+  }
+
+  fn collect(&self, ctx: &Context) -> Result<Value, io::Error> {
+    ctx
+      .uname()
+      .map(|utsname| Value::Kernel(get_system_info(utsname)))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::TempDir;
+
+  #[test]
+  fn parses_shell_quoting() {
+    let release = OsRelease::parse(concat!(
+      "# comment\n",
+      "\n",
+      "NAME=NixOS\n",
+      "ID='nixos'\n",
+      "PRETTY_NAME=\"NixOS 24.11 (Vicuna)\"\n",
+      "VARIANT=\"say \\\"hi\\\" \\$HOME \\n\"\n",
+      "BUILD_ID='a\\b'\n",
+      "VERSION=plain\\ words\n",
+      "  ID_LIKE=\"debian ubuntu\"  \n",
+    ));
 
-    offset += line_end + 1;
+    assert_eq!(release.name(), "NixOS");
+    assert_eq!(release.id(), "nixos");
+    assert_eq!(release.pretty_name(), "NixOS 24.11 (Vicuna)");
+    assert_eq!(release.get("VARIANT"), Some("say \"hi\" $HOME \\n"));
+    assert_eq!(release.get("BUILD_ID"), Some("a\\b"));
+    assert_eq!(release.get("VERSION"), Some("plain words"));
+    assert_eq!(release.id_like().collect::<Vec<_>>(), ["debian", "ubuntu"]);
   }
 
-  Ok(unknown().to_owned())
+  #[test]
+  fn skips_malformed_lines() {
+    let release = OsRelease::parse(concat!(
+      "no equals sign\n",
+      "1ID=digit\n",
+      "ID-X=dash\n",
+      "=empty\n",
+      "NAME=\"unterminated\n",
+      "ID=first\n",
+      "ID=second\n",
+    ));
+
+    assert_eq!(release.iter().collect::<Vec<_>>(), [("ID", "second")]);
+  }
+
+  #[test]
+  fn applies_defaults() {
+    let release = OsRelease::parse("");
+    assert_eq!(release.name(), "Linux");
+    assert_eq!(release.pretty_name(), "Linux");
+    assert_eq!(release.id(), "linux");
+    assert_eq!(release.id_like().next(), None);
+  }
+
+  #[test]
+  fn falls_back_to_usr_lib() {
+    let dir = TempDir::new("release");
+    let root = Root::new(dir.as_str());
+    std::fs::create_dir_all(dir.join("etc")).unwrap();
+    std::fs::create_dir_all(dir.join("usr/lib")).unwrap();
+
+    let missing = read_os_release(&root).unwrap_err();
+    assert_eq!(missing.kind(), io::ErrorKind::NotFound);
+
+    std::fs::write(dir.join("usr/lib/os-release"), "ID=fallback\n").unwrap();
+    assert_eq!(read_os_release(&root).unwrap().id(), "fallback");
+
+    std::fs::write(dir.join("etc/os-release"), "ID=preferred\n").unwrap();
+    assert_eq!(read_os_release(&root).unwrap().id(), "preferred");
+  }
 }
@@ -0,0 +1,700 @@
+//! Rendering layer that turns the plain data collected by the other modules
+//! into the colored, human-readable strings shown in the fetch.
+
+#[cfg(any(feature = "memory", feature = "storage"))]
+use std::fmt::Write as _;
+use std::{
+  borrow::Cow,
+  io::{self, Write},
+};
+
+#[cfg(feature = "desktop")] use crate::desktop::DesktopInfo;
+#[cfg(feature = "colors")] use crate::dots::print_dots;
+#[cfg(feature = "storage")] use crate::system::DiskUsage;
+#[cfg(feature = "memory")] use crate::system::MemoryInfo;
+#[cfg(feature = "uptime")] use crate::uptime::Uptime;
+use crate::{
+  Entry,
+  Fields,
+  colors::{COLORS, Color},
+  config::FieldStyle,
+  field::Value,
+  graphics::Graphic,
+  logo::{self, Logo},
+  release::KernelInfo,
+  system::UserInfo,
+  theme,
+  unknown,
+  width::{display_width, truncate},
+};
+#[cfg(any(feature = "memory", feature = "storage"))]
+use crate::{
+  config::{Thresholds, UsageDisplay},
+  terminal,
+};
+
+#[cfg(any(feature = "memory", feature = "storage"))]
+const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+
+/// Faster integer to string conversion without the formatting overhead.
+#[cfg(feature = "uptime")]
+#[inline]
+fn itoa(mut n: u64, buf: &mut [u8]) -> &str {
+  if n == 0 {
+    return "0";
+  }
+
+  let mut i = buf.len();
+  while n > 0 {
+    i -= 1;
+    buf[i] = b'0' + (n % 10) as u8;
+    n /= 10;
+  }
+
+  unsafe { std::str::from_utf8_unchecked(&buf[i..]) }
+}
+
+#[cfg(any(feature = "memory", feature = "storage"))]
+#[allow(clippy::cast_precision_loss)]
+fn gib(bytes: u64) -> f64 {
+  bytes as f64 / GIB
+}
+
+/// Renders `user@host` in the theme's colors for the header line.
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn user_info(info: &UserInfo) -> String {
+  let theme = theme::current();
+  let user = COLORS.paint(theme.user);
+  let at = COLORS.paint(theme.at);
+  let host = COLORS.paint(theme.host);
+
+  let capacity = user.len()
+    + info.username.len()
+    + at.len()
+    + 1
+    + host.len()
+    + info.hostname.len()
+    + COLORS.reset.len();
+  let mut result = String::with_capacity(capacity);
+
+  result.push_str(&user);
+  result.push_str(&info.username);
+  result.push_str(&at);
+  result.push('@');
+  result.push_str(&host);
+  result.push_str(&info.hostname);
+  result.push_str(COLORS.reset);
+
+  result
+}
+
+/// Renders the kernel as `sysname release (machine)`.
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn kernel(info: &KernelInfo) -> String {
+  // Pre-allocate capacity: sysname + " " + release + " (" + machine + ")"
+  let capacity =
+    info.sysname.len() + 1 + info.release.len() + 2 + info.machine.len() + 1;
+  let mut result = String::with_capacity(capacity);
+
+  result.push_str(&info.sysname);
+  result.push(' ');
+  result.push_str(&info.release);
+  result.push(' ');
+  result.push('(');
+  result.push_str(&info.machine);
+  result.push(')');
+  result
+}
+
+/// Renders the desktop as `desktop (Backend)`, with the first character of
+/// the backend capitalized.
+#[cfg(feature = "desktop")]
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn desktop(info: &DesktopInfo) -> String {
+  let desktop_str = info.desktop.as_deref().unwrap_or(unknown());
+  let backend_str = info.session_type.as_deref().unwrap_or(unknown());
+
+  // Pre-calculate capacity: desktop_len + " (" + backend_len + ")"
+  let mut result =
+    String::with_capacity(desktop_str.len() + backend_str.len() + 3);
+  result.push_str(desktop_str);
+  result.push(' ');
+  result.push('(');
+
+  // Capitalize first character of backend
+  if let Some(first_char) = backend_str.chars().next() {
+    result.push(first_char.to_ascii_uppercase());
+    result.push_str(&backend_str[first_char.len_utf8()..]);
+  }
+
+  result.push(')');
+  result
+}
+
+/// Renders the uptime as e.g. `1 day, 2 hours, 5 minutes`.
+#[cfg(feature = "uptime")]
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn uptime(uptime: &Uptime) -> String {
+  let days = uptime.days();
+  let hours = uptime.hours();
+  let minutes = uptime.minutes();
+
+  let mut result = String::with_capacity(32);
+  let mut buf = [0u8; 20]; // Enough for u64::MAX
+
+  if days > 0 {
+    result.push_str(itoa(days, &mut buf));
+    result.push_str(if days == 1 { " day" } else { " days" });
+  }
+  if hours > 0 {
+    if !result.is_empty() {
+      result.push_str(", ");
+    }
+    result.push_str(itoa(hours, &mut buf));
+    result.push_str(if hours == 1 { " hour" } else { " hours" });
+  }
+  if minutes > 0 {
+    if !result.is_empty() {
+      result.push_str(", ");
+    }
+    result.push_str(itoa(minutes, &mut buf));
+    result.push_str(if minutes == 1 { " minute" } else { " minutes" });
+  }
+  if result.is_empty() {
+    result.push_str("less than a minute");
+  }
+
+  result
+}
+
+/// The color of a usage percentage, as shown: the color for how high it is,
+/// going by the configured or else the default thresholds.
+#[cfg(any(feature = "memory", feature = "storage"))]
+fn usage_color(percent: f64, thresholds: Option<Thresholds>) -> Color {
+  let thresholds = thresholds.unwrap_or(Thresholds::DEFAULT);
+  theme::current().get(thresholds.role(percent))
+}
+
+/// Default number of cells in a usage bar.
+#[cfg(any(feature = "memory", feature = "storage"))]
+const BAR_WIDTH: u8 = 10;
+
+/// Writes a usage bar, e.g. `[██████░░░░]`, filled up to `percent` in
+/// `color`.
+#[cfg(any(feature = "memory", feature = "storage"))]
+fn write_bar(
+  out: &mut String,
+  percent: f64,
+  style: Option<&FieldStyle>,
+  color: &str,
+) {
+  let width = style.and_then(|s| s.bar_width).unwrap_or(BAR_WIDTH);
+  let [filled, empty] = style.and_then(|s| s.bar_chars).unwrap_or_else(|| {
+    // Block elements are missing from most console fonts
+    if terminal::is_utf8() {
+      ['█', '░']
+    } else {
+      ['#', '-']
+    }
+  });
+  #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+  let cells =
+    (percent.clamp(0.0, 100.0) * f64::from(width) / 100.0).round() as usize;
+
+  out.push('[');
+  out.push_str(color);
+  out.extend(std::iter::repeat_n(filled, cells));
+  out.push_str(COLORS.bright_black);
+  out.extend(std::iter::repeat_n(empty, usize::from(width) - cells));
+  out.push_str(COLORS.reset);
+  out.push(']');
+}
+
+/// Renders `used` out of `total` bytes as `used GiB / total GiB (percent%)`,
+/// as a bar and the percentage, or both, as set in `style`.
+#[cfg(any(feature = "memory", feature = "storage"))]
+fn usage(
+  used: u64,
+  total: u64,
+  percent: f64,
+  style: Option<&FieldStyle>,
+) -> String {
+  // Colored by the percentage as shown
+  let percent = percent.round();
+  let color =
+    COLORS.paint(usage_color(percent, style.and_then(|s| s.thresholds)));
+  let reset = COLORS.reset;
+  let display = style.and_then(|s| s.usage).unwrap_or_default();
+
+  let mut result = String::with_capacity(64);
+  if display != UsageDisplay::Bar {
+    write!(result, "{:.2} GiB / {:.2} GiB ", gib(used), gib(total)).unwrap();
+  }
+  if display == UsageDisplay::Text {
+    write!(result, "({color}{percent:.0}%{reset})").unwrap();
+  } else {
+    write_bar(&mut result, percent, style, &color);
+    write!(result, " {color}{percent:.0}%{reset}").unwrap();
+  }
+
+  result
+}
+
+/// Renders memory usage, see [`usage`].
+#[cfg(feature = "memory")]
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn memory(info: &MemoryInfo, style: Option<&FieldStyle>) -> String {
+  usage(info.used(), info.total, info.usage_percent(), style)
+}
+
+/// Renders disk usage, see [`usage`].
+#[cfg(feature = "storage")]
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn disk_usage(disk: &DiskUsage, style: Option<&FieldStyle>) -> String {
+  usage(disk.used(), disk.total, disk.usage_percent(), style)
+}
+
+/// Renders a collected value, with the field's style overrides applied.
+#[must_use]
+#[cfg_attr(
+  not(any(feature = "memory", feature = "storage")),
+  allow(unused_variables)
+)]
+pub fn value(value: &Value, style: Option<&FieldStyle>) -> String {
+  match value {
+    Value::User(info) => user_info(info),
+    Value::Os(release) => release.pretty_name().to_owned(),
+    Value::Kernel(info) => kernel(info),
+    #[cfg(feature = "desktop")]
+    Value::Desktop(info) => desktop(info),
+    #[cfg(feature = "uptime")]
+    Value::Uptime(info) => uptime(info),
+    #[cfg(feature = "memory")]
+    Value::Memory(info) => memory(info, style),
+    #[cfg(feature = "storage")]
+    Value::Disk(usage) => disk_usage(usage, style),
+    #[cfg(feature = "colors")]
+    Value::Palette => print_dots().to_owned(),
+    Value::Text(text) => text.clone(),
+  }
+}
+
+/// Columns before the logo, or before the fields when there is no logo.
+const INDENT: usize = 4;
+
+/// Columns between the logo and the fields.
+const LOGO_GAP: usize = 4;
+
+/// Columns between the longest label and the separator before the values.
+const LABEL_GAP: usize = 2;
+
+/// The label and icon of a field, with style overrides applied.
+fn label_and_icon<'a>(
+  entry: &Entry,
+  style: Option<&'a FieldStyle>,
+) -> (&'a str, &'a str) {
+  let label = style
+    .and_then(|style| style.label.as_deref())
+    .unwrap_or_else(|| entry.field.label());
+  let icon = style
+    .and_then(|style| style.icon.as_deref())
+    .unwrap_or_else(|| entry.field.icon());
+  (label, icon)
+}
+
+/// Display widths of the widest icon and label, which every row is padded to
+/// so that labels and values line up, and the room left for each row.
+#[derive(Default)]
+struct Columns {
+  icon:  usize,
+  label: usize,
+  /// Columns available to a row, if the terminal size is known.
+  room:  Option<usize>,
+}
+
+impl Columns {
+  /// Columns a row takes up besides its value.
+  const fn chrome(&self, label: &str) -> usize {
+    if label.is_empty() {
+      // The ` ~` after the title
+      2
+    } else {
+      // Icon, two spaces, label, gap, separator and a space
+      self.icon + 2 + self.label + LABEL_GAP + 2
+    }
+  }
+}
+
+/// Renders the row for a single field: icon, label, separator and value, or
+/// just the value for title fields without a label. Values too wide for the
+/// room left are cut off with an ellipsis.
+fn row(
+  entry: &Entry,
+  style: Option<&FieldStyle>,
+  rendered: &str,
+  columns: &Columns,
+) -> String {
+  let reset = COLORS.reset;
+
+  let (label, icon) = label_and_icon(entry, style);
+  let rendered = columns
+    .room
+    .and_then(|room| {
+      truncate(rendered, room.saturating_sub(columns.chrome(label)))
+    })
+    // The cut may have dropped the reset after a colored part
+    .map_or(Cow::Borrowed(rendered), |truncated| {
+      Cow::Owned(format!("{truncated}{reset}"))
+    });
+
+  if label.is_empty() {
+    return format!("{rendered} ~{reset}");
+  }
+
+  let theme = theme::current();
+  let icon_color =
+    COLORS.paint(style.and_then(|s| s.icon_color).unwrap_or(theme.icon));
+  let label_color =
+    COLORS.paint(style.and_then(|s| s.label_color).unwrap_or(theme.label));
+  let separator = COLORS.paint(theme.separator);
+  let value_color = COLORS.paint(theme.value);
+  // Only reset what was colored, to keep the output small
+  let end = |color: &str| if color.is_empty() { "" } else { reset };
+  // Return to the value color after colored parts, e.g. percentages
+  let rendered = if value_color.is_empty() || reset.is_empty() {
+    rendered
+  } else {
+    Cow::Owned(rendered.replace(reset, &format!("{reset}{value_color}")))
+  };
+
+  let icon_pad = columns.icon.saturating_sub(display_width(icon));
+  let pad = columns.label.saturating_sub(display_width(label)) + LABEL_GAP;
+  format!(
+    "{icon_color}{icon}{:icon_pad$}  \
+     {label_color}{label}{reset}{:pad$}{separator}\u{E621}{} \
+     {value_color}{rendered}{}",
+    "",
+    "",
+    end(&separator),
+    end(&value_color),
+  )
+}
+
+/// Where the logo is drawn relative to the fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Layout {
+  /// Logo on the left, fields next to it.
+  #[default]
+  Left,
+  /// Logo stacked above the fields.
+  Above,
+  /// Fields on the left, logo next to them.
+  Right,
+  /// Only the fields, without a logo.
+  InfoOnly,
+}
+
+impl Layout {
+  #[must_use]
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "left" => Some(Self::Left),
+      "above" => Some(Self::Above),
+      "right" => Some(Self::Right),
+      "none" => Some(Self::InfoOnly),
+      _ => None,
+    }
+  }
+
+  /// Whether the logo shares its lines with the fields.
+  const fn is_side_by_side(self) -> bool {
+    matches!(self, Self::Left | Self::Right)
+  }
+}
+
+/// Controls how [`print_system_info`] lays out the fetch.
+pub struct Options<'a> {
+  /// Logo drawn to the left of the fields, if any.
+  pub logo:       Option<&'a Logo>,
+  /// Color replacing the primary `${c1}` color of the logo, e.g. from an
+  /// os-release `ANSI_COLOR`.
+  pub logo_color: Option<Color>,
+  /// Per-field label, icon and color overrides.
+  pub styles:     &'a [FieldStyle],
+  /// Width of the terminal. When known, a logo that does not fit is replaced
+  /// by its small variant or left out, and values are cut off instead of
+  /// wrapping.
+  pub columns:    Option<usize>,
+  /// Height of the terminal. When known, the small variant of the logo is
+  /// drawn if the fetch would otherwise push the prompt off the screen.
+  pub lines:      Option<usize>,
+  pub layout:     Layout,
+  /// Always draw the small variant of the logo.
+  pub small:      bool,
+  /// Image drawn in place of the logo, which should then be the image's
+  /// [`placeholder`](Graphic::placeholder).
+  pub image:      Option<&'a Graphic>,
+}
+
+impl Default for Options<'_> {
+  fn default() -> Self {
+    Self {
+      logo:       Some(logo::DEFAULT),
+      logo_color: None,
+      styles:     &[],
+      columns:    None,
+      lines:      None,
+      layout:     Layout::Left,
+      small:      false,
+      image:      None,
+    }
+  }
+}
+
+/// Renders the full fetch into `out`.
+///
+/// # Errors
+///
+/// Returns an error if writing to `out` fails.
+pub fn write_system_info(
+  out: &mut impl Write,
+  fields: &Fields,
+  options: &Options,
+) -> Result<(), io::Error> {
+  let primary = options.logo_color;
+  let styles: Vec<_> = fields
+    .entries
+    .iter()
+    .map(|entry| {
+      let name = entry.field.name();
+      options.styles.iter().find(|style| style.name == name)
+    })
+    .collect();
+  let mut columns = Columns::default();
+  for (entry, &style) in fields.entries.iter().zip(&styles) {
+    let (label, icon) = label_and_icon(entry, style);
+    if !label.is_empty() {
+      columns.icon = columns.icon.max(display_width(icon));
+      columns.label = columns.label.max(display_width(label));
+    }
+  }
+
+  // Values are rendered up front, as the layout depends on their width
+  let values: Vec<String> = fields
+    .entries
+    .iter()
+    .zip(&styles)
+    .map(|(entry, &style)| {
+      entry
+        .value
+        .as_ref()
+        .map_or_else(|| unknown().to_owned(), |v| value(v, style))
+    })
+    .collect();
+  let widest = fields
+    .entries
+    .iter()
+    .zip(&styles)
+    .zip(&values)
+    .map(|((entry, &style), rendered)| {
+      let (label, _) = label_and_icon(entry, style);
+      columns.chrome(label) + display_width(rendered)
+    })
+    .max()
+    .unwrap_or(0);
+
+  let side_by_side = options.layout.is_side_by_side();
+  let too_wide = |logo: &Logo| {
+    let needed = if side_by_side {
+      INDENT + logo.width() + LOGO_GAP + widest
+    } else {
+      INDENT + logo.width()
+    };
+    options.columns.is_some_and(|terminal| needed > terminal)
+  };
+  let too_tall = |logo: &Logo| {
+    let body = if side_by_side {
+      logo.lines.len().max(fields.entries.len())
+    } else {
+      logo.lines.len() + 1 + fields.entries.len()
+    };
+    // The empty lines around the fetch, and the prompt after it
+    options.lines.is_some_and(|terminal| body + 3 > terminal)
+  };
+
+  let mut logo = options.logo.filter(|_| options.layout != Layout::InfoOnly);
+  if options.small || logo.is_some_and(too_tall) {
+    logo = logo.map(Logo::small);
+  }
+  if logo.is_some_and(too_wide) {
+    logo = logo.map(Logo::small).filter(|&small| !too_wide(small));
+  }
+  let beside = logo
+    .filter(|_| side_by_side)
+    .map_or(0, |logo| logo.width() + LOGO_GAP);
+  columns.room = options
+    .columns
+    .map(|terminal| terminal.saturating_sub(INDENT + beside));
+
+  let rows: Vec<String> = fields
+    .entries
+    .iter()
+    .zip(&styles)
+    .zip(&values)
+    .map(|((entry, &style), rendered)| row(entry, style, rendered, &columns))
+    .collect();
+
+  out.write_all(b"\n")?;
+  match options.layout {
+    Layout::Left | Layout::InfoOnly => write_left(out, logo, &rows, primary)?,
+    Layout::Above => write_above(out, logo, &rows, primary)?,
+    Layout::Right => write_right(out, logo, &rows, primary)?,
+  }
+
+  // The logo is left out when there is no room, and the image with it
+  if let (Some(image), Some(logo)) = (options.image, logo) {
+    let (lines, column) = match options.layout {
+      Layout::Above => (logo.lines.len() + 1 + rows.len(), INDENT),
+      Layout::Right => {
+        let rows_width =
+          rows.iter().map(|row| display_width(row)).max().unwrap_or(0);
+        (
+          logo.lines.len().max(rows.len()),
+          INDENT + rows_width + LOGO_GAP,
+        )
+      },
+      Layout::Left | Layout::InfoOnly => {
+        (logo.lines.len().max(rows.len()), INDENT)
+      },
+    };
+    // Go back up to the placeholder, draw over it and return
+    write!(
+      out,
+      "\x1b7\x1b[{lines}A\r\x1b[{column}C{}\x1b8",
+      image.sequence,
+    )?;
+  }
+  out.write_all(b"\n")
+}
+
+/// The logo, padded to its widest line, with the rows next to it.
+fn write_left(
+  out: &mut impl Write,
+  logo: Option<&Logo>,
+  rows: &[String],
+  primary: Option<Color>,
+) -> Result<(), io::Error> {
+  let logo_lines = logo.map_or(&[][..], |logo| logo.lines);
+  let logo_width = logo.map_or(0, |logo| logo.width() + LOGO_GAP);
+
+  for i in 0..logo_lines.len().max(rows.len()) {
+    write!(out, "{:INDENT$}", "")?;
+    let mut pad = logo_width;
+    if let Some(line) = logo_lines.get(i) {
+      logo::write_line(out, line, &COLORS, primary)?;
+      out.write_all(COLORS.reset.as_bytes())?;
+      pad -= logo::line_width(line);
+    }
+    if let Some(row) = rows.get(i) {
+      write!(out, "{:pad$}", "")?;
+      out.write_all(row.as_bytes())?;
+    }
+    out.write_all(b"\n")?;
+  }
+  Ok(())
+}
+
+/// The logo, then an empty line and the rows below it.
+fn write_above(
+  out: &mut impl Write,
+  logo: Option<&Logo>,
+  rows: &[String],
+  primary: Option<Color>,
+) -> Result<(), io::Error> {
+  if let Some(logo) = logo {
+    for line in logo.lines {
+      write!(out, "{:INDENT$}", "")?;
+      logo::write_line(out, line, &COLORS, primary)?;
+      writeln!(out, "{}", COLORS.reset)?;
+    }
+    out.write_all(b"\n")?;
+  }
+  for row in rows {
+    writeln!(out, "{:INDENT$}{row}", "")?;
+  }
+  Ok(())
+}
+
+/// The rows, padded to the widest one, with the logo next to them.
+fn write_right(
+  out: &mut impl Write,
+  logo: Option<&Logo>,
+  rows: &[String],
+  primary: Option<Color>,
+) -> Result<(), io::Error> {
+  let logo_lines = logo.map_or(&[][..], |logo| logo.lines);
+  let widths: Vec<usize> = rows.iter().map(|row| display_width(row)).collect();
+  let rows_width = widths.iter().copied().max().unwrap_or(0);
+
+  for i in 0..logo_lines.len().max(rows.len()) {
+    write!(out, "{:INDENT$}", "")?;
+    if let Some(row) = rows.get(i) {
+      out.write_all(row.as_bytes())?;
+    }
+    if let Some(line) = logo_lines.get(i) {
+      let pad = rows_width - widths.get(i).copied().unwrap_or(0) + LOGO_GAP;
+      write!(out, "{:pad$}", "")?;
+      logo::write_line(out, line, &COLORS, primary)?;
+      out.write_all(COLORS.reset.as_bytes())?;
+    }
+    out.write_all(b"\n")?;
+  }
+  Ok(())
+}
+
+/// Renders the full fetch and writes it to stdout.
+///
+/// # Errors
+///
+/// Returns an error if the output cannot be written to stdout.
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn print_system_info(
+  fields: &Fields,
+  options: &Options,
+) -> Result<(), io::Error> {
+  // Large enough for the built-in logos, but custom logos and long values can
+  // take more
+  let mut buf = Vec::with_capacity(2048);
+  write_system_info(&mut buf, fields, options)?;
+
+  // Direct syscall to avoid stdout buffering allocation
+  let mut rest = &buf[..];
+  while !rest.is_empty() {
+    let written = unsafe {
+      libc::write(libc::STDOUT_FILENO, rest.as_ptr().cast(), rest.len())
+    };
+    if written < 0 {
+      let err = io::Error::last_os_error();
+      if err.kind() == io::ErrorKind::Interrupted {
+        continue;
+      }
+      return Err(err);
+    }
+    if written == 0 {
+      return Err(io::Error::new(
+        io::ErrorKind::WriteZero,
+        "failed to write to stdout",
+      ));
+    }
+    #[allow(clippy::cast_sign_loss)]
+    let written = written as usize;
+    rest = &rest[written..];
+  }
+  Ok(())
+}
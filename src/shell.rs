@@ -0,0 +1,204 @@
+//! Shell-evaluable output. Every collected value is printed as a
+//! `MICROFETCH_<FIELD>_<PART>=value` assignment, quoted so that
+//! `eval "$(microfetch --shell)"` is safe in any POSIX shell.
+//!
+//! Fields that could not be collected are left out, so that they stay unset.
+
+#[cfg(any(feature = "uptime", feature = "memory", feature = "storage"))]
+use std::fmt::Write as _;
+
+use crate::{Fields, field::Value};
+
+/// Appends `s` wrapped in single quotes. Embedded single quotes are closed,
+/// escaped and reopened, which is the only escape POSIX shells need.
+pub fn write_quoted(out: &mut String, s: &str) {
+  out.push('\'');
+  for c in s.chars() {
+    if c == '\'' {
+      out.push_str("'\\''");
+    } else {
+      out.push(c);
+    }
+  }
+  out.push('\'');
+}
+
+/// Appends a `MICROFETCH_<FIELD>_<PART>='value'` line.
+fn write_str(out: &mut String, field: &str, part: &str, value: &str) {
+  write_name(out, field, part);
+  write_quoted(out, value);
+  out.push('\n');
+}
+
+#[cfg(any(feature = "uptime", feature = "memory", feature = "storage"))]
+fn write_u64(out: &mut String, field: &str, part: &str, value: u64) {
+  write_name(out, field, part);
+  writeln!(out, "{value}").unwrap();
+}
+
+fn write_name(out: &mut String, field: &str, part: &str) {
+  out.push_str("MICROFETCH_");
+  for c in field.chars().chain(Some('_')).chain(part.chars()) {
+    out.push(if c.is_ascii_alphanumeric() {
+      c.to_ascii_uppercase()
+    } else {
+      '_'
+    });
+  }
+  out.push('=');
+}
+
+fn write_value(out: &mut String, field: &str, value: &Value) {
+  match value {
+    Value::User(info) => {
+      write_str(out, field, "username", &info.username);
+      write_str(out, field, "hostname", &info.hostname);
+    },
+    Value::Kernel(info) => {
+      write_str(out, field, "sysname", &info.sysname);
+      write_str(out, field, "release", &info.release);
+      write_str(out, field, "machine", &info.machine);
+    },
+    #[cfg(feature = "desktop")]
+    Value::Desktop(info) => {
+      if let Some(desktop) = &info.desktop {
+        write_str(out, field, "desktop", desktop);
+      }
+      if let Some(session_type) = &info.session_type {
+        write_str(out, field, "session_type", session_type);
+      }
+    },
+    #[cfg(feature = "uptime")]
+    Value::Uptime(uptime) => write_u64(out, field, "seconds", uptime.seconds),
+    #[cfg(feature = "memory")]
+    Value::Memory(info) => {
+      write_u64(out, field, "total", info.total);
+      write_u64(out, field, "available", info.available);
+      write_u64(out, field, "used", info.used());
+    },
+    #[cfg(feature = "storage")]
+    Value::Disk(usage) => {
+      write_str(out, field, "mount_point", &usage.mount_point);
+      write_u64(out, field, "total", usage.total);
+      write_u64(out, field, "free", usage.free);
+      write_u64(out, field, "available", usage.available);
+      write_u64(out, field, "used", usage.used());
+    },
+    #[cfg(feature = "colors")]
+    Value::Palette => {},
+    Value::Os(release) => {
+      write_str(out, field, "name", release.pretty_name());
+      write_str(out, field, "id", release.id());
+    },
+    Value::Text(text) => write_str(out, field, "name", text),
+  }
+}
+
+/// Serializes the collected fields as shell variable assignments, one per
+/// line.
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn to_shell(fields: &Fields) -> String {
+  let mut out = String::with_capacity(512);
+  for entry in &fields.entries {
+    if let Some(value) = &entry.value {
+      write_value(&mut out, entry.field.name(), value);
+    }
+  }
+  out
+}
+
+#[cfg(test)]
+mod tests {
+  use std::process::Command;
+
+  use super::*;
+  use crate::{Entry, field, system::UserInfo};
+
+  const NASTY: &[&str] = &[
+    "",
+    "plain",
+    "it's",
+    "''",
+    "$HOME ${PATH} $(id)",
+    "`id`",
+    "back\\slash\\",
+    "two\nlines\n",
+    "\"double\" & ; | * ? ~ #",
+    "tab\there",
+  ];
+
+  /// Evaluates `script` with `sh` and prints the value of `variable`.
+  fn eval(script: &str, variable: &str) -> String {
+    let output = Command::new("sh")
+      .arg("-c")
+      .arg(format!("eval \"$0\"; printf %s \"${variable}\""))
+      .arg(script)
+      .output()
+      .unwrap();
+    assert!(output.status.success(), "{script:?}");
+    String::from_utf8(output.stdout).unwrap()
+  }
+
+  #[test]
+  fn quotes() {
+    let quoted = |s: &str| {
+      let mut out = String::new();
+      write_quoted(&mut out, s);
+      out
+    };
+    assert_eq!(quoted(""), "''");
+    assert_eq!(quoted("a b"), "'a b'");
+    assert_eq!(quoted("it's"), r"'it'\''s'");
+    assert_eq!(quoted("$x `y` \\"), "'$x `y` \\'");
+    assert_eq!(quoted("a\nb"), "'a\nb'");
+  }
+
+  #[test]
+  fn evaluates_to_the_same_bytes() {
+    let user = field::find("user").unwrap();
+    for &value in NASTY {
+      let fields = Fields {
+        entries: vec![Entry {
+          field: user,
+          value: Some(Value::User(UserInfo {
+            username: value.to_owned(),
+            hostname: format!("{value}'"),
+          })),
+        }],
+        errors:  Vec::new(),
+        release: None,
+      };
+      let script = to_shell(&fields);
+      assert_eq!(eval(&script, "MICROFETCH_USER_USERNAME"), value);
+      assert_eq!(
+        eval(&script, "MICROFETCH_USER_HOSTNAME"),
+        format!("{value}'")
+      );
+    }
+  }
+
+  #[test]
+  fn names_are_identifiers() {
+    let is_identifier = |name: &str| {
+      let word = |b: u8| b.is_ascii_uppercase() || b == b'_';
+      let mut bytes = name.bytes();
+      bytes.next().is_some_and(word)
+        && bytes.all(|b| word(b) || b.is_ascii_digit())
+    };
+
+    for field in field::REGISTRY {
+      let mut out = String::new();
+      write_name(&mut out, field.name(), "session_type");
+      let name = out.strip_suffix('=').unwrap();
+      assert!(is_identifier(name), "{name:?}");
+    }
+
+    let fields =
+      crate::snapshot::parse(crate::snapshot::MOCK, field::REGISTRY).unwrap();
+    for line in to_shell(&fields).lines() {
+      let (name, _) = line.split_once('=').unwrap();
+      assert!(is_identifier(name), "{line:?}");
+    }
+  }
+}
@@ -0,0 +1,325 @@
+//! Snapshots decouple collecting the fields from rendering them.
+//!
+//! A snapshot is simply the [`json`](crate::json) output saved to a file.
+//! Loading one rebuilds [`Fields`] from it, so that another machine's data can
+//! be rendered, a bug report reproduced exactly, or a screenshot taken with
+//! fixed values. [`MOCK`] is a built-in snapshot with made-up values, which
+//! makes the rendered output byte-for-byte reproducible.
+
+#[cfg(feature = "storage")] use std::borrow::Cow;
+use std::{error::Error, fmt, io};
+
+#[cfg(feature = "desktop")] use crate::desktop::DesktopInfo;
+#[cfg(feature = "storage")] use crate::system::DiskUsage;
+#[cfg(feature = "memory")] use crate::system::MemoryInfo;
+#[cfg(feature = "uptime")] use crate::uptime::Uptime;
+use crate::{
+  Entry,
+  Fields,
+  error::FieldError,
+  field::{self, Field, Value},
+  json::{self, Json, SCHEMA_VERSION},
+  release::{KernelInfo, OsRelease},
+  syscall::read_file_to_end,
+  system::UserInfo,
+};
+
+/// A fixed, made-up system used by `--mock`.
+pub const MOCK: &str = r#"{
+  "version": 1,
+  "fields": {
+    "user": { "username": "user", "hostname": "nixos" },
+    "os": "NixOS 25.05 (Warbler)",
+    "kernel": { "sysname": "Linux", "release": "6.12.30", "machine": "x86_64" },
+    "shell": "bash",
+    "uptime": { "seconds": 93784 },
+    "desktop": { "desktop": "Hyprland", "session_type": "wayland" },
+    "memory": { "total": 17179869184, "available": 6871947674 },
+    "storage": {
+      "mount_point": "/",
+      "total": 512110190592,
+      "free": 301365030912,
+      "available": 275594625024
+    }
+  },
+  "os_release": {
+    "NAME": "NixOS",
+    "ID": "nixos",
+    "PRETTY_NAME": "NixOS 25.05 (Warbler)",
+    "VERSION_ID": "25.05",
+    "ANSI_COLOR": "0;38;2;126;186;228"
+  },
+  "errors": []
+}
+"#;
+
+/// Rebuilds the given fields, in order, from a snapshot.
+///
+/// Fields that were not saved in the snapshot are left out, and fields that
+/// failed to collect when it was taken have no value here either. The color
+/// palette carries no data and is therefore always available.
+///
+/// # Errors
+///
+/// Returns an error if `source` is not valid JSON, was written by an
+/// incompatible version, or a saved field does not have the expected shape.
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn parse(
+  source: &str,
+  fields: &[&'static dyn Field],
+) -> Result<Fields, SnapshotError> {
+  let document = json::parse(source).map_err(SnapshotError::Syntax)?;
+
+  let version = document.get("version").and_then(Json::as_u64);
+  if version != Some(u64::from(SCHEMA_VERSION)) {
+    return Err(SnapshotError::Version(version));
+  }
+
+  let saved = document
+    .get("fields")
+    .filter(|saved| matches!(saved, Json::Object(_)))
+    .ok_or(SnapshotError::Invalid("fields"))?;
+
+  let mut entries = Vec::with_capacity(fields.len());
+  for &field in fields {
+    #[cfg(feature = "colors")]
+    if field.name() == "colors" {
+      entries.push(Entry {
+        field,
+        value: Some(Value::Palette),
+      });
+      continue;
+    }
+
+    let value = match saved.get(field.name()) {
+      None => continue,
+      Some(Json::Null) => None,
+      Some(json) => {
+        Some(
+          value(field.name(), json, &document)
+            .ok_or_else(|| SnapshotError::Invalid(field.name()))?,
+        )
+      },
+    };
+    entries.push(Entry { field, value });
+  }
+
+  let errors = match document.get("errors") {
+    Some(Json::Array(errors)) => errors.iter().filter_map(error).collect(),
+    _ => return Err(SnapshotError::Invalid("errors")),
+  };
+
+  // Still needed to pick the logo when the `os` field is not shown
+  let release = entries
+    .iter()
+    .all(|entry| entry.field.name() != "os")
+    .then(|| os_release(&document))
+    .flatten();
+
+  Ok(Fields {
+    entries,
+    errors,
+    release,
+  })
+}
+
+/// The full os-release, which is saved separately next to the fields, with
+/// the pretty name from the `os` field. `None` if neither was saved.
+fn os_release(document: &Json) -> Option<OsRelease> {
+  let members = match document.get("os_release") {
+    Some(Json::Object(members)) => Some(members),
+    _ => None,
+  };
+  let pretty_name = document
+    .get("fields")
+    .and_then(|fields| fields.get("os"))
+    .and_then(Json::as_str);
+  if members.is_none() && pretty_name.is_none() {
+    return None;
+  }
+
+  let mut release = OsRelease::default();
+  for (key, value) in members.into_iter().flatten() {
+    if let Some(value) = value.as_str() {
+      release.insert(key.clone(), value.to_owned());
+    }
+  }
+  if let Some(pretty_name) = pretty_name {
+    release.insert("PRETTY_NAME".to_owned(), pretty_name.to_owned());
+  }
+  Some(release)
+}
+
+/// Rebuilds a recorded error, if its field is known to this build.
+fn error(json: &Json) -> Option<FieldError> {
+  let field = field::find(json.get("field")?.as_str()?)?;
+  let message = json.get("message")?.as_str()?;
+  Some(FieldError::new(field.name(), io::Error::other(message)))
+}
+
+fn string(json: &Json, key: &str) -> Option<String> {
+  json.get(key)?.as_str().map(str::to_owned)
+}
+
+#[cfg(any(feature = "uptime", feature = "memory", feature = "storage"))]
+fn number(json: &Json, key: &str) -> Option<u64> {
+  json.get(key)?.as_u64()
+}
+
+/// Rebuilds the value of the field called `name`, or `None` if `json` does
+/// not have the expected shape.
+fn value(name: &str, json: &Json, document: &Json) -> Option<Value> {
+  Some(match name {
+    "os" => {
+      json.as_str()?;
+      Value::Os(os_release(document)?)
+    },
+    "user" => {
+      Value::User(UserInfo {
+        username: string(json, "username")?,
+        hostname: string(json, "hostname")?,
+      })
+    },
+    "kernel" => {
+      Value::Kernel(KernelInfo {
+        sysname: string(json, "sysname")?,
+        release: string(json, "release")?,
+        machine: string(json, "machine")?,
+      })
+    },
+    #[cfg(feature = "desktop")]
+    "desktop" => {
+      Value::Desktop(DesktopInfo {
+        // Both parts are nullable
+        desktop:      string(json, "desktop"),
+        session_type: string(json, "session_type"),
+      })
+    },
+    #[cfg(feature = "uptime")]
+    "uptime" => {
+      Value::Uptime(Uptime {
+        seconds: number(json, "seconds")?,
+      })
+    },
+    #[cfg(feature = "memory")]
+    "memory" => {
+      Value::Memory(MemoryInfo {
+        total:     number(json, "total")?,
+        available: number(json, "available")?,
+      })
+    },
+    #[cfg(feature = "storage")]
+    "storage" => {
+      Value::Disk(DiskUsage {
+        mount_point: Cow::Owned(string(json, "mount_point")?),
+        total:       number(json, "total")?,
+        free:        number(json, "free")?,
+        available:   number(json, "available")?,
+      })
+    },
+    _ => Value::Text(json.as_str()?.to_owned()),
+  })
+}
+
+/// Reads a snapshot from `path` and rebuilds the given fields from it, see
+/// [`parse`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or is not a valid snapshot.
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn load(
+  path: &str,
+  fields: &[&'static dyn Field],
+) -> Result<Fields, SnapshotError> {
+  let mut buffer = Vec::with_capacity(4096);
+  read_file_to_end(path, &mut buffer).map_err(SnapshotError::Io)?;
+
+  let source = std::str::from_utf8(&buffer).map_err(|_| {
+    SnapshotError::Io(io::Error::new(
+      io::ErrorKind::InvalidData,
+      "snapshot is not valid UTF-8",
+    ))
+  })?;
+  parse(source, fields)
+}
+
+/// Writes the collected fields to `path` as a snapshot.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be written.
+pub fn save(path: &str, fields: &Fields) -> Result<(), SnapshotError> {
+  std::fs::write(path, json::to_json(fields)).map_err(SnapshotError::Io)
+}
+
+#[derive(Debug)]
+pub enum SnapshotError {
+  Io(io::Error),
+  Syntax(json::ParseError),
+  /// The snapshot has no version, or one this build cannot read.
+  Version(Option<u64>),
+  /// The named part of the snapshot is missing or has the wrong shape.
+  Invalid(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Io(err) => write!(f, "snapshot: {err}"),
+      Self::Syntax(err) => write!(f, "snapshot: {err}"),
+      Self::Version(Some(version)) => {
+        write!(f, "snapshot: unsupported version {version}")
+      },
+      Self::Version(None) => write!(f, "snapshot: missing version"),
+      Self::Invalid(name) => write!(f, "snapshot: invalid '{name}'"),
+    }
+  }
+}
+
+impl Error for SnapshotError {
+  fn source(&self) -> Option<&(dyn Error + 'static)> {
+    match self {
+      Self::Io(err) => Some(err),
+      Self::Syntax(err) => Some(err),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::TempDir;
+
+  #[test]
+  fn keeps_os_release_without_os_field() {
+    let selected: Vec<_> = field::REGISTRY
+      .iter()
+      .copied()
+      .filter(|field| field.name() != "os")
+      .collect();
+    let fields = parse(MOCK, &selected).unwrap();
+
+    let release = fields.os_release().unwrap();
+    assert_eq!(release.id(), "nixos");
+    assert_eq!(release.get("PRETTY_NAME"), Some("NixOS 25.05 (Warbler)"));
+  }
+
+  #[test]
+  fn round_trips_large_snapshots() {
+    let padding = format!(r#""PADDING": "{}","#, "x".repeat(16384));
+    let source = MOCK.replacen(r#""NAME": "NixOS","#, &padding, 1);
+    let fields = parse(&source, field::REGISTRY).unwrap();
+
+    let temp = TempDir::new("snapshot");
+    let path = temp.join("snapshot.json");
+    let path = path.to_str().unwrap();
+    save(path, &fields).unwrap();
+    let loaded = load(path, field::REGISTRY);
+
+    let saved = json::to_json(&fields);
+    assert!(saved.len() > 16384);
+    assert_eq!(json::to_json(&loaded.unwrap()), saved);
+  }
+}
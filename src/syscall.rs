@@ -8,9 +8,7 @@
 //! Supports `x86_64` and `aarch64` architectures. Riscv support will be
 //! implemented when and ONLY WHEN I can be bothered to work on it.
 
-use std::io;
-
-use crate::last_os_error;
+use std::{ffi::CString, io};
 
 /// Direct syscall to open a file
 ///
@@ -171,10 +169,34 @@ pub unsafe fn sys_close(fd: i32) -> i32 {
   }
 }
 
+/// Opens `path` read-only, returning the file descriptor.
 #[inline]
-#[cold]
-fn path_too_long() -> io::Result<usize> {
-  Err(io::Error::new(io::ErrorKind::InvalidInput, "Path too long"))
+fn open_read_only(path: &str) -> io::Result<i32> {
+  const O_RDONLY: i32 = 0;
+
+  // Use stack-allocated buffer for null-terminated path (max 256 bytes)
+  let path_bytes = path.as_bytes();
+  let mut path_buf = [0u8; 256];
+  let long_path;
+  let path_ptr = if path_bytes.len() < path_buf.len() {
+    path_buf[..path_bytes.len()].copy_from_slice(path_bytes);
+    // XXX: Already zero-terminated since array is initialized to zeros
+    path_buf.as_ptr()
+  } else {
+    // Paths below an alternate root can be longer
+    long_path = CString::new(path).map_err(|_| {
+      io::Error::new(io::ErrorKind::InvalidInput, "path contains a NUL byte")
+    })?;
+    long_path.as_ptr().cast()
+  };
+
+  // Raw syscalls report failure by returning `-errno` rather than setting
+  // `errno`, so the error has to be rebuilt from the return value
+  let fd = unsafe { sys_open(path_ptr, O_RDONLY) };
+  if fd < 0 {
+    return Err(io::Error::from_raw_os_error(-fd));
+  }
+  Ok(fd)
 }
 
 /// Read entire file using direct syscalls. This avoids libc overhead and can be
@@ -185,29 +207,15 @@ fn path_too_long() -> io::Result<usize> {
 /// Returns an error if the file cannot be opened or read
 #[inline]
 pub fn read_file_fast(path: &str, buffer: &mut [u8]) -> io::Result<usize> {
-  const O_RDONLY: i32 = 0;
-
-  // Use stack-allocated buffer for null-terminated path (max 256 bytes)
-  let path_bytes = path.as_bytes();
-  if path_bytes.len() >= 256 {
-    return path_too_long();
-  }
-
-  let mut path_buf = [0u8; 256];
-  path_buf[..path_bytes.len()].copy_from_slice(path_bytes);
-  // XXX: Already zero-terminated since array is initialized to zeros
+  let fd = open_read_only(path)?;
 
   unsafe {
-    let fd = sys_open(path_buf.as_ptr(), O_RDONLY);
-    if fd < 0 {
-      return last_os_error();
-    }
-
     let bytes_read = sys_read(fd, buffer.as_mut_ptr(), buffer.len());
     let _ = sys_close(fd);
 
     if bytes_read < 0 {
-      return last_os_error();
+      #[allow(clippy::cast_possible_truncation)]
+      return Err(io::Error::from_raw_os_error(-bytes_read as i32));
     }
 
     #[allow(clippy::cast_sign_loss)]
@@ -216,3 +224,60 @@ pub fn read_file_fast(path: &str, buffer: &mut [u8]) -> io::Result<usize> {
     }
   }
 }
+
+/// Like [`read_file_fast`], but keeps reading until the end of the file,
+/// growing `buffer` as needed. The contents are appended to `buffer`.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be opened or read
+pub fn read_file_to_end(path: &str, buffer: &mut Vec<u8>) -> io::Result<usize> {
+  let fd = open_read_only(path)?;
+  let start = buffer.len();
+
+  loop {
+    if buffer.capacity() == buffer.len() {
+      buffer.reserve(1024);
+    }
+    let spare = buffer.spare_capacity_mut();
+    let bytes_read =
+      unsafe { sys_read(fd, spare.as_mut_ptr().cast(), spare.len()) };
+
+    if bytes_read < 0 {
+      let _ = unsafe { sys_close(fd) };
+      #[allow(clippy::cast_possible_truncation)]
+      return Err(io::Error::from_raw_os_error(-bytes_read as i32));
+    }
+    if bytes_read == 0 {
+      break;
+    }
+
+    // SAFETY: the kernel initialized the first `bytes_read` spare bytes
+    #[allow(clippy::cast_sign_loss)]
+    unsafe {
+      buffer.set_len(buffer.len() + bytes_read as usize);
+    }
+  }
+
+  let _ = unsafe { sys_close(fd) };
+  Ok(buffer.len() - start)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::TempDir;
+
+  #[test]
+  fn reads_long_paths() {
+    let temp = TempDir::new("syscall");
+    let dir = temp.join(&format!("{}/{}", "a".repeat(200), "b".repeat(200)));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("file");
+    std::fs::write(&path, "contents").unwrap();
+
+    let mut buffer = Vec::new();
+    read_file_to_end(path.to_str().unwrap(), &mut buffer).unwrap();
+    assert_eq!(buffer, b"contents");
+  }
+}
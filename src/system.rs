@@ -1,108 +1,138 @@
-use std::{ffi::CStr, fmt::Write as _, io, mem::MaybeUninit};
+use std::io;
+#[cfg(feature = "storage")]
+use std::{borrow::Cow, ffi::CString, mem::MaybeUninit};
 
-use crate::{colors::COLORS, last_os_error, syscall::read_file_fast, UtsName};
+#[cfg(any(feature = "memory", feature = "storage"))]
+use crate::Root;
+#[cfg(feature = "storage")] use crate::last_os_error;
+#[cfg(feature = "memory")]
+use crate::syscall::read_file_fast;
+use crate::{
+  UtsName,
+  field::{Context, Field, Value},
+  getenv,
+};
 
 #[inline]
 #[cold]
-const fn unknown_user() -> &'static str { "unknown_user" }
+const fn unknown_user() -> &'static str {
+  "unknown_user"
+}
 
 #[inline]
 #[cold]
-const fn unknown_host() -> &'static str { "unknown_host" }
+const fn unknown_host() -> &'static str {
+  "unknown_host"
+}
 
+/// The current user and the hostname of the machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserInfo {
+  pub username: String,
+  pub hostname: String,
+}
+
+/// Gets the current user from `$USER` and the hostname from `utsname`. The
+/// hostname falls back to a placeholder if `uname` failed.
 #[must_use]
 #[cfg_attr(feature = "hotpath", hotpath::measure)]
-pub fn get_username_and_hostname(utsname: &UtsName) -> String {
-  let username = unsafe {
-    let ptr = libc::getenv(c"USER".as_ptr());
-    if ptr.is_null() {
-      unknown_user()
-    } else {
-      CStr::from_ptr(ptr).to_str().unwrap_or_else(|_| unknown_user())
-    }
-  };
-  let hostname = utsname.nodename().to_str().unwrap_or_else(|_| unknown_host());
-
-  let capacity = COLORS.yellow.len()
-    + username.len()
-    + COLORS.red.len()
-    + 1
-    + COLORS.green.len()
-    + hostname.len()
-    + COLORS.reset.len();
-  let mut result = String::with_capacity(capacity);
-
-  result.push_str(COLORS.yellow);
-  result.push_str(username);
-  result.push_str(COLORS.red);
-  result.push('@');
-  result.push_str(COLORS.green);
-  result.push_str(hostname);
-  result.push_str(COLORS.reset);
+pub fn get_user_info(utsname: Option<&UtsName>) -> UserInfo {
+  let username = getenv(c"USER").unwrap_or_else(unknown_user);
+  let hostname = utsname
+    .and_then(|utsname| utsname.nodename().to_str().ok())
+    .unwrap_or(unknown_host());
 
-  result
+  UserInfo {
+    username: username.to_owned(),
+    hostname: hostname.to_owned(),
+  }
 }
 
+#[cfg(feature = "shell")]
 #[inline]
 #[cold]
-const fn unknown_shell() -> &'static str { "unknown_shell" }
+const fn unknown_shell() -> &'static str {
+  "unknown_shell"
+}
 
+#[cfg(feature = "shell")]
 #[must_use]
 #[cfg_attr(feature = "hotpath", hotpath::measure)]
 pub fn get_shell() -> String {
-  unsafe {
-    let ptr = libc::getenv(c"SHELL".as_ptr());
-    if ptr.is_null() {
-      return unknown_shell().into();
-    }
+  let shell = getenv(c"SHELL").unwrap_or_else(unknown_shell);
+  shell
+    .rsplit_once('/')
+    .map_or(shell, |(_, name)| name)
+    .into()
+}
 
-    let bytes = CStr::from_ptr(ptr).to_bytes();
-    let start = bytes.iter().rposition(|&b| b == b'/').map_or(0, |i| i + 1);
-    let name = std::str::from_utf8_unchecked(&bytes[start..]);
-    name.into()
+/// Usage of a mounted filesystem, in bytes.
+#[cfg(feature = "storage")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskUsage {
+  /// Mount point the usage was queried for.
+  pub mount_point: Cow<'static, str>,
+  pub total:       u64,
+  /// Free space, including blocks reserved for the superuser.
+  pub free:        u64,
+  /// Free space available to unprivileged users.
+  pub available:   u64,
+}
+
+#[cfg(feature = "storage")]
+impl DiskUsage {
+  #[must_use]
+  pub const fn used(&self) -> u64 {
+    self.total.saturating_sub(self.available)
+  }
+
+  /// Percentage of the filesystem that is in use, from 0 to 100.
+  #[must_use]
+  #[allow(clippy::cast_precision_loss)]
+  pub fn usage_percent(&self) -> f64 {
+    if self.total == 0 {
+      return 0.0;
+    }
+    self.used() as f64 / self.total as f64 * 100.0
   }
 }
 
-/// Gets the root disk usage information.
+/// Gets the usage of the filesystem mounted at `root`.
 ///
 /// # Errors
 ///
 /// Returns an error if the filesystem information cannot be retrieved.
+#[cfg(feature = "storage")]
 #[cfg_attr(feature = "hotpath", hotpath::measure)]
-#[allow(clippy::cast_precision_loss)]
-pub fn get_root_disk_usage() -> Result<String, io::Error> {
+pub fn get_root_disk_usage(root: &Root) -> Result<DiskUsage, io::Error> {
   let mut vfs = MaybeUninit::uninit();
-  let path = b"/\0";
+  let path = match root.path("/") {
+    Cow::Borrowed(_) => Cow::Borrowed(c"/"),
+    Cow::Owned(path) => {
+      Cow::Owned(
+        CString::new(path)
+          .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?,
+      )
+    },
+  };
 
-  if unsafe { libc::statvfs(path.as_ptr().cast(), vfs.as_mut_ptr()) } != 0 {
+  if unsafe { libc::statvfs(path.as_ptr(), vfs.as_mut_ptr()) } != 0 {
     return last_os_error();
   }
 
   let vfs = unsafe { vfs.assume_init() };
   let block_size = vfs.f_bsize;
-  let total_blocks = vfs.f_blocks;
-  let available_blocks = vfs.f_bavail;
-
-  let total_size = block_size * total_blocks;
-  let used_size = total_size - (block_size * available_blocks);
 
-  let total_size = total_size as f64 / (1024.0 * 1024.0 * 1024.0);
-  let used_size = used_size as f64 / (1024.0 * 1024.0 * 1024.0);
-  let usage = (used_size / total_size) * 100.0;
-
-  let mut result = String::with_capacity(64);
-  write!(
-    result,
-    "{used_size:.2} GiB / {total_size:.2} GiB ({cyan}{usage:.0}%{reset})",
-    cyan = COLORS.cyan,
-    reset = COLORS.reset,
-  )
-  .unwrap();
-
-  Ok(result)
+  Ok(DiskUsage {
+    mount_point: Cow::Borrowed("/"),
+    total:       block_size * vfs.f_blocks,
+    free:        block_size * vfs.f_bfree,
+    available:   block_size * vfs.f_bavail,
+  })
 }
 
 /// Fast integer parsing without stdlib overhead
+#[cfg(feature = "memory")]
 #[inline]
 fn parse_u64_fast(s: &[u8]) -> u64 {
   let mut result = 0u64;
@@ -116,81 +146,173 @@ fn parse_u64_fast(s: &[u8]) -> u64 {
   result
 }
 
+/// System memory information, in bytes.
+#[cfg(feature = "memory")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryInfo {
+  pub total:     u64,
+  pub available: u64,
+}
+
+#[cfg(feature = "memory")]
+impl MemoryInfo {
+  #[must_use]
+  pub const fn used(&self) -> u64 {
+    self.total.saturating_sub(self.available)
+  }
+
+  /// Percentage of memory that is in use, from 0 to 100.
+  #[must_use]
+  #[allow(clippy::cast_precision_loss)]
+  pub fn usage_percent(&self) -> f64 {
+    if self.total == 0 {
+      return 0.0;
+    }
+    self.used() as f64 / self.total as f64 * 100.0
+  }
+}
+
 /// Gets the system memory usage information.
 ///
 /// # Errors
 ///
-/// Returns an error if `/proc/meminfo` cannot be read.
+/// Returns an error if `/proc/meminfo` under `root` cannot be read.
+#[cfg(feature = "memory")]
 #[cfg_attr(feature = "hotpath", hotpath::measure)]
-pub fn get_memory_usage() -> Result<String, io::Error> {
-  #[cfg_attr(feature = "hotpath", hotpath::measure)]
-  fn parse_memory_info() -> Result<(f64, f64), io::Error> {
-    let mut total_memory_kb = 0u64;
-    let mut available_memory_kb = 0u64;
-    let mut buffer = [0u8; 1024];
-
-    // Use fast syscall-based file reading
-    let bytes_read = read_file_fast("/proc/meminfo", &mut buffer)?;
-    let meminfo = &buffer[..bytes_read];
-
-    // Fast scanning for MemTotal and MemAvailable
-    let mut offset = 0;
-    let mut found_total = false;
-    let mut found_available = false;
-
-    while offset < meminfo.len() && (!found_total || !found_available) {
-      let remaining = &meminfo[offset..];
-
-      // Find newline or end
-      let line_end = remaining
-        .iter()
-        .position(|&b| b == b'\n')
-        .unwrap_or(remaining.len());
-      let line = &remaining[..line_end];
-
-      if line.starts_with(b"MemTotal:") {
-        // Skip "MemTotal:" and whitespace
-        let mut pos = 9;
-        while pos < line.len() && line[pos].is_ascii_whitespace() {
-          pos += 1;
-        }
-        total_memory_kb = parse_u64_fast(&line[pos..]);
-        found_total = true;
-      } else if line.starts_with(b"MemAvailable:") {
-        // Skip "MemAvailable:" and whitespace
-        let mut pos = 13;
-        while pos < line.len() && line[pos].is_ascii_whitespace() {
-          pos += 1;
-        }
-        available_memory_kb = parse_u64_fast(&line[pos..]);
-        found_available = true;
-      }
+pub fn get_memory_info(root: &Root) -> Result<MemoryInfo, io::Error> {
+  let mut total_memory_kb = 0u64;
+  let mut available_memory_kb = 0u64;
+  let mut buffer = [0u8; 1024];
+
+  // Use fast syscall-based file reading
+  let bytes_read = read_file_fast(&root.path("/proc/meminfo"), &mut buffer)?;
+  let meminfo = &buffer[..bytes_read];
+
+  // Fast scanning for MemTotal and MemAvailable
+  let mut offset = 0;
+  let mut found_total = false;
+  let mut found_available = false;
+
+  while offset < meminfo.len() && (!found_total || !found_available) {
+    let remaining = &meminfo[offset..];
 
-      offset += line_end + 1;
+    // Find newline or end
+    let line_end = remaining
+      .iter()
+      .position(|&b| b == b'\n')
+      .unwrap_or(remaining.len());
+    let line = &remaining[..line_end];
+
+    if line.starts_with(b"MemTotal:") {
+      // Skip "MemTotal:" and whitespace
+      let mut pos = 9;
+      while pos < line.len() && line[pos].is_ascii_whitespace() {
+        pos += 1;
+      }
+      total_memory_kb = parse_u64_fast(&line[pos..]);
+      found_total = true;
+    } else if line.starts_with(b"MemAvailable:") {
+      // Skip "MemAvailable:" and whitespace
+      let mut pos = 13;
+      while pos < line.len() && line[pos].is_ascii_whitespace() {
+        pos += 1;
+      }
+      available_memory_kb = parse_u64_fast(&line[pos..]);
+      found_available = true;
     }
 
-    #[allow(clippy::cast_precision_loss)]
-    let total_memory_gb = total_memory_kb as f64 / 1024.0 / 1024.0;
-    #[allow(clippy::cast_precision_loss)]
-    let available_memory_gb = available_memory_kb as f64 / 1024.0 / 1024.0;
-    let used_memory_gb = total_memory_gb - available_memory_gb;
+    offset += line_end + 1;
+  }
 
-    Ok((used_memory_gb, total_memory_gb))
+  Ok(MemoryInfo {
+    total:     total_memory_kb * 1024,
+    available: available_memory_kb * 1024,
+  })
+}
+
+/// The `user@host` title line.
+pub struct UserField;
+
+impl Field for UserField {
+  fn name(&self) -> &'static str {
+    "user"
+  }
+
+  fn label(&self) -> &'static str {
+    ""
+  }
+
+  fn icon(&self) -> &'static str {
+    ""
+  }
+
+  fn collect(&self, ctx: &Context) -> Result<Value, io::Error> {
+    Ok(Value::User(get_user_info(ctx.uname().ok())))
+  }
+}
+
+#[cfg(feature = "shell")]
+pub struct ShellField;
+
+#[cfg(feature = "shell")]
+impl Field for ShellField {
+  fn name(&self) -> &'static str {
+    "shell"
+  }
+
+  fn label(&self) -> &'static str {
+    "Shell"
+  }
+
+  fn icon(&self) -> &'static str {
+    "\u{E795}"
+  }
+
+  fn collect(&self, _: &Context) -> Result<Value, io::Error> {
+    Ok(Value::Text(get_shell()))
+  }
+}
+
+#[cfg(feature = "memory")]
+pub struct MemoryField;
+
+#[cfg(feature = "memory")]
+impl Field for MemoryField {
+  fn name(&self) -> &'static str {
+    "memory"
+  }
+
+  fn label(&self) -> &'static str {
+    "Memory"
+  }
+
+  fn icon(&self) -> &'static str {
+    "\u{F035B}"
   }
 
-  let (used_memory, total_memory) = parse_memory_info()?;
-  #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
-  let percentage_used = (used_memory / total_memory * 100.0).round() as u64;
+  fn collect(&self, ctx: &Context) -> Result<Value, io::Error> {
+    get_memory_info(ctx.root()).map(Value::Memory)
+  }
+}
 
-  let mut result = String::with_capacity(64);
-  write!(
-    result,
-    "{used_memory:.2} GiB / {total_memory:.2} GiB \
-     ({cyan}{percentage_used}%{reset})",
-    cyan = COLORS.cyan,
-    reset = COLORS.reset,
-  )
-  .unwrap();
+#[cfg(feature = "storage")]
+pub struct StorageField;
 
-  Ok(result)
+#[cfg(feature = "storage")]
+impl Field for StorageField {
+  fn name(&self) -> &'static str {
+    "storage"
+  }
+
+  fn label(&self) -> &'static str {
+    "Storage (/)"
+  }
+
+  fn icon(&self) -> &'static str {
+    "\u{F194E}"
+  }
+
+  fn collect(&self, ctx: &Context) -> Result<Value, io::Error> {
+    get_root_disk_usage(ctx.root()).map(Value::Disk)
+  }
 }
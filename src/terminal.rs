@@ -0,0 +1,251 @@
+//! Size and background color of the terminal the fetch is printed to.
+
+use std::{
+  ffi::CStr,
+  mem::MaybeUninit,
+  time::{Duration, Instant},
+};
+
+use crate::getenv;
+
+/// How long to wait for the terminal to answer a query. Local terminals
+/// answer within a millisecond or two, but SSH adds a round trip.
+const QUERY_TIMEOUT: Duration = Duration::from_millis(150);
+
+/// Asks for the background color with OSC 11, followed by a request for the
+/// primary device attributes. Every terminal answers the latter, so once its
+/// answer arrives there is no point in waiting for the former any longer.
+const BACKGROUND_QUERY: &[u8] = b"\x1b]11;?\x1b\\\x1b[c";
+
+/// Asks the terminal on stdout for its size.
+fn winsize() -> Option<libc::winsize> {
+  let mut size = MaybeUninit::<libc::winsize>::uninit();
+  // Fails when stdout is not a terminal, e.g. when piped
+  let result = unsafe {
+    libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, size.as_mut_ptr())
+  };
+  if result != 0 {
+    return None;
+  }
+  Some(unsafe { size.assume_init() })
+}
+
+/// A positive number from the environment.
+fn env_size(name: &CStr) -> Option<usize> {
+  getenv(name)?.trim().parse().ok().filter(|&size| size > 0)
+}
+
+/// Whether the locale's character set is UTF-8, going by the first of
+/// `LC_ALL`, `LC_CTYPE` and `LANG` that is set.
+#[must_use]
+pub fn is_utf8() -> bool {
+  getenv(c"LC_ALL")
+    .or_else(|| getenv(c"LC_CTYPE"))
+    .or_else(|| getenv(c"LANG"))
+    .is_some_and(|locale| {
+      let locale = locale.to_ascii_lowercase();
+      locale.contains("utf-8") || locale.contains("utf8")
+    })
+}
+
+/// Width of the terminal in columns, from `TIOCGWINSZ` or, if stdout is not a
+/// terminal, `$COLUMNS`. `None` if neither is available.
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn columns() -> Option<usize> {
+  winsize()
+    .map(|size| usize::from(size.ws_col))
+    .filter(|&columns| columns > 0)
+    .or_else(|| env_size(c"COLUMNS"))
+}
+
+/// Height of the terminal in lines, from `TIOCGWINSZ` or, if stdout is not a
+/// terminal, `$LINES`. `None` if neither is available.
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn lines() -> Option<usize> {
+  winsize()
+    .map(|size| usize::from(size.ws_row))
+    .filter(|&lines| lines > 0)
+    .or_else(|| env_size(c"LINES"))
+}
+
+/// Size of a character cell in pixels, if the terminal reports it.
+#[must_use]
+pub fn cell_size() -> Option<(usize, usize)> {
+  let size = winsize()?;
+  let width = usize::from(size.ws_xpixel).checked_div(usize::from(size.ws_col));
+  let height =
+    usize::from(size.ws_ypixel).checked_div(usize::from(size.ws_row));
+  width
+    .zip(height)
+    .filter(|&(width, height)| width > 0 && height > 0)
+}
+
+/// Whether `reply` ends with the answer to the device attributes request,
+/// `ESC [ ? ... c`.
+fn has_attributes(reply: &[u8]) -> bool {
+  reply
+    .windows(3)
+    .rposition(|w| w == b"\x1b[?")
+    .is_some_and(|start| reply[start..].ends_with(b"c"))
+}
+
+/// Writes `query` to the terminal `fd` and collects its answers until the
+/// device attributes arrive, for at most [`QUERY_TIMEOUT`]. Whatever arrived
+/// by then is returned, as the other answers may be complete without them.
+fn read_reply(fd: libc::c_int, query: &[u8]) -> Option<Vec<u8>> {
+  let written = unsafe { libc::write(fd, query.as_ptr().cast(), query.len()) };
+  if usize::try_from(written).ok() != Some(query.len()) {
+    return None;
+  }
+
+  let deadline = Instant::now() + QUERY_TIMEOUT;
+  let mut reply = Vec::new();
+  while !has_attributes(&reply) && reply.len() <= 256 {
+    let left = deadline.saturating_duration_since(Instant::now());
+    if left.is_zero() {
+      break;
+    }
+
+    let mut poll = libc::pollfd {
+      fd,
+      events: libc::POLLIN,
+      revents: 0,
+    };
+    let timeout = i32::try_from(left.as_millis()).unwrap_or(i32::MAX);
+    match unsafe { libc::poll(&raw mut poll, 1, timeout) } {
+      0 => break,
+      ..0 => {
+        if std::io::Error::last_os_error().kind()
+          == std::io::ErrorKind::Interrupted
+        {
+          continue;
+        }
+        break;
+      },
+      _ => {},
+    }
+
+    let mut buffer = [0u8; 64];
+    let read = unsafe { libc::read(fd, buffer.as_mut_ptr().cast(), 64) };
+    let Some(read) = usize::try_from(read).ok().filter(|&read| read > 0) else {
+      break;
+    };
+    reply.extend_from_slice(&buffer[..read]);
+  }
+  Some(reply)
+}
+
+/// Sends `query` to the terminal `fd` in raw mode, so that the answers can be
+/// read as soon as they arrive and are not echoed.
+fn query(fd: libc::c_int, query: &[u8]) -> Option<Vec<u8>> {
+  let mut saved = MaybeUninit::<libc::termios>::uninit();
+  if unsafe { libc::tcgetattr(fd, saved.as_mut_ptr()) } != 0 {
+    return None;
+  }
+  let saved = unsafe { saved.assume_init() };
+
+  let mut raw = saved;
+  raw.c_lflag &= !(libc::ICANON | libc::ECHO);
+  raw.c_cc[libc::VMIN] = 0;
+  raw.c_cc[libc::VTIME] = 0;
+  if unsafe { libc::tcsetattr(fd, libc::TCSANOW, &raw const raw) } != 0 {
+    return None;
+  }
+
+  let reply = read_reply(fd, query);
+  // Answers still in flight would otherwise end up at the shell prompt
+  unsafe {
+    libc::tcflush(fd, libc::TCIFLUSH);
+    libc::tcsetattr(fd, libc::TCSANOW, &raw const saved);
+  }
+  reply
+}
+
+/// Parses the `rgb:RRRR/GGGG/BBBB` color in an OSC 11 answer, with 1 to 4 hex
+/// digits per channel.
+fn parse_background(reply: &[u8]) -> Option<[u8; 3]> {
+  let start = reply.windows(4).position(|w| w == b"]11;")? + 4;
+  let reply = std::str::from_utf8(&reply[start..]).ok()?;
+  let end = reply.find(['\x07', '\x1b'])?;
+  let spec = &reply[..end];
+  let spec = spec
+    .strip_prefix("rgb:")
+    .or_else(|| spec.strip_prefix("rgba:"))?;
+
+  let mut channels = spec.split('/').map(|hex| {
+    let digits = u32::try_from(hex.len())
+      .ok()
+      .filter(|len| (1..=4).contains(len))?;
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    // Scale to 8 bits, e.g. `ffff` and `f` are both 255
+    u8::try_from(value * 255 / (16u32.pow(digits) - 1)).ok()
+  });
+  Some([channels.next()??, channels.next()??, channels.next()??])
+}
+
+/// The background color of the controlling terminal, asked for with OSC 11.
+/// `None` if there is no controlling terminal, or it does not answer in time.
+#[must_use]
+#[cfg_attr(feature = "hotpath", hotpath::measure)]
+pub fn background() -> Option<[u8; 3]> {
+  let fd = unsafe {
+    libc::open(
+      c"/dev/tty".as_ptr(),
+      libc::O_RDWR | libc::O_NOCTTY | libc::O_CLOEXEC,
+    )
+  };
+  if fd < 0 {
+    return None;
+  }
+  let reply = query(fd, BACKGROUND_QUERY);
+  unsafe { libc::close(fd) };
+  parse_background(&reply?)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_background() {
+    let parse = |reply: &str| parse_background(reply.as_bytes());
+    assert_eq!(
+      parse("\x1b]11;rgb:ffff/ffff/ffff\x1b\\\x1b[?62;22c"),
+      Some([255, 255, 255])
+    );
+    assert_eq!(parse("\x1b]11;rgb:1e1e/2020/3030\x07"), Some([30, 32, 48]));
+    assert_eq!(parse("\x1b]11;rgb:f/8/0\x07"), Some([255, 136, 0]));
+    assert_eq!(parse("\x1b]11;rgb:fff/800/000\x07"), Some([255, 127, 0]));
+    assert_eq!(
+      parse("\x1b]11;rgba:0000/8080/ffff/ffff\x07"),
+      Some([0, 128, 255])
+    );
+  }
+
+  #[test]
+  fn rejects_malformed_backgrounds() {
+    for reply in [
+      "\x1b[?62;22c",
+      "\x1b]11;rgb:ffff/ffff/ffff",
+      "\x1b]11;#ffffff\x07",
+      "\x1b]11;rgb:ffff/ffff\x07",
+      "\x1b]11;rgb:fffff/0/0\x07",
+      "\x1b]11;rgb:/0/0\x07",
+      "\x1b]11;rgb:gg/0/0\x07",
+      "\x1b]10;rgb:0/0/0\x07",
+    ] {
+      assert_eq!(parse_background(reply.as_bytes()), None, "{reply:?}");
+    }
+  }
+
+  #[test]
+  fn waits_for_device_attributes() {
+    assert!(has_attributes(b"\x1b[?62;22c"));
+    assert!(has_attributes(b"\x1b]11;rgb:0/0/0\x07\x1b[?1;2c"));
+    assert!(!has_attributes(b"\x1b]11;rgb:0/0/0\x07"));
+    assert!(!has_attributes(b"\x1b]11;rgb:0/0/0\x07\x1b[?62;2"));
+    assert!(!has_attributes(b""));
+  }
+}
@@ -0,0 +1,212 @@
+//! A reader for compiled terminfo entries, the binary files written by `tic`,
+//! so that terminal capabilities can be looked up without ncurses.
+//!
+//! Only the numeric capabilities are decoded, as the number of colors is all
+//! microfetch needs to know.
+
+use std::io;
+
+use crate::{getenv, syscall::read_file_to_end};
+
+/// Magic number of the legacy format, with 16-bit numbers.
+const MAGIC: u16 = 0o432;
+/// Magic number of the extended number format, with 32-bit numbers.
+const MAGIC_32BIT: u16 = 0o1036;
+
+/// Index of `colors` among the predefined numeric capabilities.
+const COLORS: usize = 13;
+
+/// The numeric capabilities of a terminal.
+pub struct Terminfo {
+  numbers: Vec<i32>,
+}
+
+fn invalid(message: &'static str) -> io::Error {
+  io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Directories searched for entries, in the same order as ncurses.
+fn directories() -> Vec<String> {
+  let mut dirs = Vec::new();
+  if let Some(dir) = getenv(c"TERMINFO") {
+    dirs.push(dir.to_owned());
+  }
+  if let Some(home) = getenv(c"HOME") {
+    dirs.push(format!("{home}/.terminfo"));
+  }
+  if let Some(list) = getenv(c"TERMINFO_DIRS") {
+    dirs.extend(
+      list
+        .split(':')
+        .filter(|dir| !dir.is_empty())
+        .map(str::to_owned),
+    );
+  }
+  for dir in ["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo"] {
+    dirs.push(dir.to_owned());
+  }
+  dirs
+}
+
+impl Terminfo {
+  /// Decodes a compiled entry.
+  ///
+  /// # Errors
+  ///
+  /// Returns an error if `bytes` is not a compiled terminfo entry, or is
+  /// truncated.
+  pub fn parse(bytes: &[u8]) -> io::Result<Self> {
+    let header = bytes
+      .get(..12)
+      .ok_or_else(|| invalid("truncated terminfo header"))?;
+    let field = |i: usize| u16::from_le_bytes([header[i], header[i + 1]]);
+    let width = match field(0) {
+      MAGIC => 2,
+      MAGIC_32BIT => 4,
+      _ => return Err(invalid("not a compiled terminfo entry")),
+    };
+    let names = usize::from(field(2));
+    let booleans = usize::from(field(4));
+    let count = usize::from(field(6));
+
+    // Numbers are aligned to an even offset
+    let mut start = 12 + names + booleans;
+    start += start % 2;
+    let data = bytes
+      .get(start..start + count * width)
+      .ok_or_else(|| invalid("truncated terminfo entry"))?;
+
+    let numbers = if width == 2 {
+      data
+        .chunks_exact(2)
+        .map(|n| i32::from(i16::from_le_bytes([n[0], n[1]])))
+        .collect()
+    } else {
+      data
+        .chunks_exact(4)
+        .map(|n| i32::from_le_bytes([n[0], n[1], n[2], n[3]]))
+        .collect()
+    };
+
+    Ok(Self { numbers })
+  }
+
+  /// Finds and decodes the entry for `term`, e.g. `xterm-256color`. `None` if
+  /// there is no such entry, or it cannot be read.
+  #[must_use]
+  #[cfg_attr(feature = "hotpath", hotpath::measure)]
+  pub fn load(term: &str) -> Option<Self> {
+    if term.is_empty() || term.contains('/') || term.starts_with('.') {
+      return None;
+    }
+    let first = term.chars().next()?;
+
+    let mut bytes = Vec::new();
+    for dir in directories() {
+      // Entries are grouped by their first letter, which macOS spells in hex
+      for path in [
+        format!("{dir}/{first}/{term}"),
+        format!("{dir}/{:x}/{term}", u32::from(first)),
+      ] {
+        bytes.clear();
+        if read_file_to_end(&path, &mut bytes).is_ok() {
+          return Self::parse(&bytes).ok();
+        }
+      }
+    }
+    None
+  }
+
+  /// The entry for `$TERM`.
+  #[must_use]
+  pub fn from_env() -> Option<Self> {
+    Self::load(getenv(c"TERM")?)
+  }
+
+  /// The numeric capability at `index`, `None` if absent or cancelled.
+  fn number(&self, index: usize) -> Option<u32> {
+    self
+      .numbers
+      .get(index)
+      .and_then(|&number| u32::try_from(number).ok())
+  }
+
+  /// The number of colors the terminal supports, the `colors` capability.
+  #[must_use]
+  pub fn colors(&self) -> Option<u32> {
+    self.number(COLORS)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Compiles an entry with the given numbers, as `tic` would.
+  fn entry(magic: u16, numbers: &[i32]) -> Vec<u8> {
+    let names = b"test|made up terminal\0";
+    let booleans = [1, 0, 1];
+    let count = u16::try_from(numbers.len()).unwrap();
+
+    let mut bytes = Vec::new();
+    for field in [magic, 22, 3, count, 0, 0] {
+      bytes.extend(field.to_le_bytes());
+    }
+    bytes.extend(names);
+    bytes.extend(booleans);
+    // Padding to the even offset of the numbers
+    bytes.push(0);
+    for &number in numbers {
+      if magic == MAGIC {
+        bytes.extend(i16::try_from(number).unwrap().to_le_bytes());
+      } else {
+        bytes.extend(number.to_le_bytes());
+      }
+    }
+    bytes
+  }
+
+  fn colors(magic: u16, colors: i32) -> Vec<u8> {
+    let mut numbers = vec![-1; COLORS + 2];
+    numbers[0] = 80;
+    numbers[COLORS] = colors;
+    entry(magic, &numbers)
+  }
+
+  #[test]
+  fn reads_colors() {
+    let legacy = Terminfo::parse(&colors(MAGIC, 256)).unwrap();
+    assert_eq!(legacy.colors(), Some(256));
+    assert_eq!(legacy.number(0), Some(80));
+
+    let extended = Terminfo::parse(&colors(MAGIC_32BIT, 1 << 24)).unwrap();
+    assert_eq!(extended.colors(), Some(1 << 24));
+  }
+
+  #[test]
+  fn missing_colors() {
+    let absent = Terminfo::parse(&colors(MAGIC, -1)).unwrap();
+    assert_eq!(absent.colors(), None);
+    let cancelled = Terminfo::parse(&colors(MAGIC, -2)).unwrap();
+    assert_eq!(cancelled.colors(), None);
+    let short = Terminfo::parse(&entry(MAGIC, &[80, 8])).unwrap();
+    assert_eq!(short.colors(), None);
+  }
+
+  #[test]
+  fn rejects_malformed_entries() {
+    let mut bad_magic = colors(MAGIC, 8);
+    bad_magic[0] ^= 1;
+    for bytes in [&bad_magic[..], &colors(MAGIC, 8)[..20], &[0o32, 1]] {
+      let err = Terminfo::parse(bytes).err().unwrap();
+      assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+  }
+
+  #[test]
+  fn refuses_paths_as_names() {
+    for term in ["", "../x/xterm", "x/xterm", ".hidden"] {
+      assert!(Terminfo::load(term).is_none(), "{term:?}");
+    }
+  }
+}
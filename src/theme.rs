@@ -0,0 +1,181 @@
+//! Themes assign a [`Color`] to every part of the fetch, e.g. the labels or
+//! the separator, so that the whole look can be changed at once.
+//!
+//! The active theme is set once at startup with [`set`], and read by the
+//! renderers with [`current`], much like [`COLORS`](crate::colors::COLORS).
+
+use std::sync::OnceLock;
+
+use crate::colors::Color;
+pub use crate::config::syntax::Role;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+  pub name:           &'static str,
+  pub logo_primary:   Color,
+  pub logo_secondary: Color,
+  pub user:           Color,
+  pub at:             Color,
+  pub host:           Color,
+  pub icon:           Color,
+  pub label:          Color,
+  pub separator:      Color,
+  pub value:          Color,
+  pub accent:         Color,
+  pub good:           Color,
+  pub warning:        Color,
+  pub critical:       Color,
+}
+
+impl Theme {
+  #[must_use]
+  pub const fn get(&self, role: Role) -> Color {
+    match role {
+      Role::LogoPrimary => self.logo_primary,
+      Role::LogoSecondary => self.logo_secondary,
+      Role::User => self.user,
+      Role::At => self.at,
+      Role::Host => self.host,
+      Role::Icon => self.icon,
+      Role::Label => self.label,
+      Role::Separator => self.separator,
+      Role::Value => self.value,
+      Role::Accent => self.accent,
+      Role::Good => self.good,
+      Role::Warning => self.warning,
+      Role::Critical => self.critical,
+    }
+  }
+
+  pub const fn set(&mut self, role: Role, color: Color) {
+    let slot = match role {
+      Role::LogoPrimary => &mut self.logo_primary,
+      Role::LogoSecondary => &mut self.logo_secondary,
+      Role::User => &mut self.user,
+      Role::At => &mut self.at,
+      Role::Host => &mut self.host,
+      Role::Icon => &mut self.icon,
+      Role::Label => &mut self.label,
+      Role::Separator => &mut self.separator,
+      Role::Value => &mut self.value,
+      Role::Accent => &mut self.accent,
+      Role::Good => &mut self.good,
+      Role::Warning => &mut self.warning,
+      Role::Critical => &mut self.critical,
+    };
+    *slot = color;
+  }
+}
+
+/// The classic look, in the terminal's own colors.
+pub const DEFAULT: Theme = Theme {
+  name:           "default",
+  logo_primary:   Color::Blue,
+  logo_secondary: Color::Cyan,
+  user:           Color::Yellow,
+  at:             Color::Red,
+  host:           Color::Green,
+  icon:           Color::Cyan,
+  label:          Color::Blue,
+  separator:      Color::Default,
+  value:          Color::Default,
+  accent:         Color::Cyan,
+  good:           Color::Green,
+  warning:        Color::Yellow,
+  critical:       Color::Red,
+};
+
+/// [`DEFAULT`] with the bright variants of its colors.
+pub const BRIGHT: Theme = Theme {
+  name:           "bright",
+  logo_primary:   Color::BrightBlue,
+  logo_secondary: Color::BrightCyan,
+  user:           Color::BrightYellow,
+  at:             Color::BrightRed,
+  host:           Color::BrightGreen,
+  icon:           Color::BrightCyan,
+  label:          Color::BrightBlue,
+  separator:      Color::Default,
+  value:          Color::Default,
+  accent:         Color::BrightCyan,
+  good:           Color::BrightGreen,
+  warning:        Color::BrightYellow,
+  critical:       Color::BrightRed,
+};
+
+/// Shades of gray only.
+pub const MONO: Theme = Theme {
+  name:           "mono",
+  logo_primary:   Color::White,
+  logo_secondary: Color::BrightBlack,
+  user:           Color::BrightWhite,
+  at:             Color::BrightBlack,
+  host:           Color::BrightWhite,
+  icon:           Color::BrightBlack,
+  label:          Color::White,
+  separator:      Color::BrightBlack,
+  value:          Color::Default,
+  accent:         Color::BrightWhite,
+  good:           Color::BrightBlack,
+  warning:        Color::White,
+  critical:       Color::BrightWhite,
+};
+
+/// The Gruvbox palette, from the 256-color range.
+pub const GRUVBOX: Theme = Theme {
+  name:           "gruvbox",
+  logo_primary:   Color::Fixed(109),
+  logo_secondary: Color::Fixed(108),
+  user:           Color::Fixed(214),
+  at:             Color::Fixed(167),
+  host:           Color::Fixed(142),
+  icon:           Color::Fixed(108),
+  label:          Color::Fixed(109),
+  separator:      Color::Fixed(245),
+  value:          Color::Default,
+  accent:         Color::Fixed(208),
+  good:           Color::Fixed(142),
+  warning:        Color::Fixed(214),
+  critical:       Color::Fixed(167),
+};
+
+/// The Nord palette, in truecolor.
+pub const NORD: Theme = Theme {
+  name:           "nord",
+  logo_primary:   Color::Rgb(0x5E, 0x81, 0xAC),
+  logo_secondary: Color::Rgb(0x88, 0xC0, 0xD0),
+  user:           Color::Rgb(0xEB, 0xCB, 0x8B),
+  at:             Color::Rgb(0xBF, 0x61, 0x6A),
+  host:           Color::Rgb(0xA3, 0xBE, 0x8C),
+  icon:           Color::Rgb(0x88, 0xC0, 0xD0),
+  label:          Color::Rgb(0x81, 0xA1, 0xC1),
+  separator:      Color::Rgb(0x4C, 0x56, 0x6A),
+  value:          Color::Default,
+  accent:         Color::Rgb(0x8F, 0xBC, 0xBB),
+  good:           Color::Rgb(0xA3, 0xBE, 0x8C),
+  warning:        Color::Rgb(0xEB, 0xCB, 0x8B),
+  critical:       Color::Rgb(0xBF, 0x61, 0x6A),
+};
+
+/// Every built-in theme, by name.
+pub static THEMES: &[&Theme] = &[&DEFAULT, &BRIGHT, &GRUVBOX, &MONO, &NORD];
+
+/// Looks up a built-in theme by name.
+#[must_use]
+pub fn find(name: &str) -> Option<&'static Theme> {
+  THEMES.iter().copied().find(|theme| theme.name == name)
+}
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// Selects the theme to render with. Only has an effect if called before
+/// [`current`] is first called.
+pub fn set(theme: Theme) {
+  let _ = THEME.set(theme);
+}
+
+/// The theme to render with, [`DEFAULT`] unless another one was [`set`].
+#[must_use]
+pub fn current() -> &'static Theme {
+  THEME.get_or_init(|| DEFAULT)
+}
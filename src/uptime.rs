@@ -1,23 +1,11 @@
 use std::{io, mem::MaybeUninit};
 
-use crate::last_os_error;
-
-/// Faster integer to string conversion without the formatting overhead.
-#[inline]
-fn itoa(mut n: u64, buf: &mut [u8]) -> &str {
-  if n == 0 {
-    return "0";
-  }
-
-  let mut i = buf.len();
-  while n > 0 {
-    i -= 1;
-    buf[i] = b'0' + (n % 10) as u8;
-    n /= 10;
-  }
-
-  unsafe { std::str::from_utf8_unchecked(&buf[i..]) }
-}
+use crate::{
+  Root,
+  field::{Context, Field, Value},
+  last_os_error,
+  syscall::read_file_fast,
+};
 
 /// Direct `sysinfo` syscall using inline assembly
 ///
@@ -65,52 +53,117 @@ unsafe fn sys_sysinfo(info: *mut libc::sysinfo) -> i64 {
   }
 }
 
-/// Gets the current system uptime.
+/// Time elapsed since boot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Uptime {
+  pub seconds: u64,
+}
+
+impl Uptime {
+  #[must_use]
+  pub const fn days(&self) -> u64 {
+    self.seconds / 86400
+  }
+
+  #[must_use]
+  pub const fn hours(&self) -> u64 {
+    (self.seconds / 3600) % 24
+  }
+
+  #[must_use]
+  pub const fn minutes(&self) -> u64 {
+    (self.seconds / 60) % 60
+  }
+}
+
+/// Gets the uptime of the system at `root`.
+///
+/// The running system is asked directly through `sysinfo`. An alternate root
+/// has no kernel of its own to ask, so its `/proc/uptime` is read instead.
 ///
 /// # Errors
 ///
 /// Returns an error if the system uptime cannot be retrieved.
 #[cfg_attr(feature = "hotpath", hotpath::measure)]
-pub fn get_current() -> Result<String, io::Error> {
-  let uptime_seconds = {
-    let mut info = MaybeUninit::uninit();
-    if unsafe { sys_sysinfo(info.as_mut_ptr()) } != 0 {
-      return last_os_error();
-    }
-    #[allow(clippy::cast_sign_loss)]
-    unsafe {
-      info.assume_init().uptime as u64
-    }
-  };
+pub fn get_current(root: &Root) -> Result<Uptime, io::Error> {
+  if !root.is_host() {
+    return read_proc_uptime(root);
+  }
 
-  let days = uptime_seconds / 86400;
-  let hours = (uptime_seconds / 3600) % 24;
-  let minutes = (uptime_seconds / 60) % 60;
+  let mut info = MaybeUninit::uninit();
+  if unsafe { sys_sysinfo(info.as_mut_ptr()) } != 0 {
+    return last_os_error();
+  }
+  #[allow(clippy::cast_sign_loss)]
+  let seconds = unsafe { info.assume_init().uptime as u64 };
+
+  Ok(Uptime { seconds })
+}
 
-  let mut result = String::with_capacity(32);
-  let mut buf = [0u8; 20]; // Enough for u64::MAX
+/// Reads the whole seconds from the first column of `/proc/uptime`.
+fn read_proc_uptime(root: &Root) -> Result<Uptime, io::Error> {
+  let mut buffer = [0u8; 64];
+  let bytes_read = read_file_fast(&root.path("/proc/uptime"), &mut buffer)?;
+  let content = &buffer[..bytes_read];
 
-  if days > 0 {
-    result.push_str(itoa(days, &mut buf));
-    result.push_str(if days == 1 { " day" } else { " days" });
+  let malformed =
+    || io::Error::new(io::ErrorKind::InvalidData, "malformed /proc/uptime");
+
+  let digits = content.iter().take_while(|b| b.is_ascii_digit()).count();
+  if digits == 0 {
+    return Err(malformed());
   }
-  if hours > 0 {
-    if !result.is_empty() {
-      result.push_str(", ");
-    }
-    result.push_str(itoa(hours, &mut buf));
-    result.push_str(if hours == 1 { " hour" } else { " hours" });
+
+  let seconds = content[..digits].iter().try_fold(0u64, |acc, &b| {
+    acc.checked_mul(10)?.checked_add(u64::from(b - b'0'))
+  });
+
+  seconds
+    .map(|seconds| Uptime { seconds })
+    .ok_or_else(malformed)
+}
+
+pub struct UptimeField;
+
+impl Field for UptimeField {
+  fn name(&self) -> &'static str {
+    "uptime"
   }
-  if minutes > 0 {
-    if !result.is_empty() {
-      result.push_str(", ");
-    }
-    result.push_str(itoa(minutes, &mut buf));
-    result.push_str(if minutes == 1 { " minute" } else { " minutes" });
+
+  fn label(&self) -> &'static str {
+    "Uptime"
+  }
+
+  fn icon(&self) -> &'static str {
+    "\u{F017}"
+  }
+
+  fn collect(&self, ctx: &Context) -> Result<Value, io::Error> {
+    get_current(ctx.root()).map(Value::Uptime)
   }
-  if result.is_empty() {
-    result.push_str("less than a minute");
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::TempDir;
+
+  /// Reads `content` as the `/proc/uptime` of a throwaway root.
+  fn parse(content: &str) -> Result<Uptime, io::Error> {
+    let dir = TempDir::new("uptime");
+    std::fs::create_dir_all(dir.join("proc")).unwrap();
+    std::fs::write(dir.join("proc/uptime"), content).unwrap();
+    read_proc_uptime(&Root::new(dir.as_str()))
   }
 
-  Ok(result)
+  #[test]
+  fn proc_uptime() {
+    assert_eq!(parse("93784.52 371234.10\n").unwrap().seconds, 93784);
+    let max = parse("18446744073709551615.00 0.00\n").unwrap();
+    assert_eq!(max.seconds, u64::MAX);
+    for content in ["18446744073709551616.00 0.00\n", ".5 0.00\n", ""] {
+      let err = parse(content).unwrap_err();
+      assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{content:?}");
+    }
+  }
 }
@@ -0,0 +1,182 @@
+//! Terminal display width of strings, so that columns line up no matter what
+//! the logo, labels and icons are made of.
+//!
+//! This is a small approximation of `wcwidth`: escape sequences and combining
+//! characters take no space, East Asian wide characters and emoji take two
+//! columns, and everything else, including Nerd Font glyphs from the private
+//! use areas, takes one.
+
+/// Number of terminal columns `c` occupies.
+#[must_use]
+pub const fn char_width(c: char) -> usize {
+  match c as u32 {
+    // Control characters
+    0x00..=0x1F
+    | 0x7F..=0x9F
+    // Combining marks
+    | 0x0300..=0x036F
+    | 0x1AB0..=0x1AFF
+    | 0x1DC0..=0x1DFF
+    | 0x20D0..=0x20FF
+    | 0xFE20..=0xFE2F
+    // Zero-width spaces, joiners, direction marks and the BOM
+    | 0x200B..=0x200F
+    | 0x2028..=0x202E
+    | 0x2060..=0x2064
+    | 0xFEFF
+    // Variation selectors
+    | 0xFE00..=0xFE0F
+    | 0xE0100..=0xE01EF => 0,
+    // Hangul Jamo, CJK, Hangul syllables and fullwidth forms
+    0x1100..=0x115F
+    | 0x2E80..=0x303E
+    | 0x3041..=0x33FF
+    | 0x3400..=0x4DBF
+    | 0x4E00..=0x9FFF
+    | 0xA000..=0xA4CF
+    | 0xA960..=0xA97F
+    | 0xAC00..=0xD7A3
+    | 0xF900..=0xFAFF
+    | 0xFE10..=0xFE19
+    | 0xFE30..=0xFE6F
+    | 0xFF00..=0xFF60
+    | 0xFFE0..=0xFFE6
+    // Emoji
+    | 0x1F300..=0x1F64F
+    | 0x1F680..=0x1F6FF
+    | 0x1F900..=0x1F9FF
+    // CJK extensions
+    | 0x20000..=0x2FFFD
+    | 0x30000..=0x3FFFD => 2,
+    _ => 1,
+  }
+}
+
+/// The visible characters of `s` and their byte offsets, skipping over ANSI
+/// escape sequences.
+struct Visible<'a> {
+  chars: std::str::CharIndices<'a>,
+}
+
+impl Iterator for Visible<'_> {
+  type Item = (usize, char);
+
+  fn next(&mut self) -> Option<Self::Item> {
+    loop {
+      let (i, c) = self.chars.next()?;
+      if c != '\x1b' {
+        return Some((i, c));
+      }
+      match self.chars.next().map(|(_, c)| c) {
+        // CSI, e.g. colors: parameters up to a final byte in `@..=~`
+        Some('[') => {
+          for (_, c) in self.chars.by_ref() {
+            if ('@'..='~').contains(&c) {
+              break;
+            }
+          }
+        },
+        // OSC, e.g. hyperlinks: terminated by BEL or `ESC \`
+        Some(']') => {
+          while let Some((_, c)) = self.chars.next() {
+            if c == '\x07'
+              || (c == '\x1b'
+                && self.chars.next().map(|(_, c)| c) == Some('\\'))
+            {
+              break;
+            }
+          }
+        },
+        _ => {},
+      }
+    }
+  }
+}
+
+fn visible(s: &str) -> Visible<'_> {
+  Visible {
+    chars: s.char_indices(),
+  }
+}
+
+/// Number of terminal columns `s` occupies once printed. ANSI escape
+/// sequences, such as the ones from [`Colors`](crate::colors::Colors), are
+/// skipped.
+#[must_use]
+pub fn display_width(s: &str) -> usize {
+  visible(s).map(|(_, c)| char_width(c)).sum()
+}
+
+/// Shortens `s` to at most `max` columns, replacing the cut off part with an
+/// ellipsis. Escape sequences before the cut are kept. Returns `None` if `s`
+/// already fits.
+#[must_use]
+pub fn truncate(s: &str, max: usize) -> Option<String> {
+  if display_width(s) <= max {
+    return None;
+  }
+  if max == 0 {
+    return Some(String::new());
+  }
+
+  let mut width = 0;
+  let mut cut = s.len();
+  for (i, c) in visible(s) {
+    width += char_width(c);
+    // Leave room for the ellipsis
+    if width > max - 1 {
+      cut = i;
+      break;
+    }
+  }
+
+  Some(format!("{}…", &s[..cut]))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn char_widths() {
+    assert_eq!(char_width('a'), 1);
+    assert_eq!(char_width('\u{F313}'), 1);
+    assert_eq!(char_width('\u{2588}'), 1);
+    assert_eq!(char_width('日'), 2);
+    assert_eq!(char_width('한'), 2);
+    assert_eq!(char_width('Ａ'), 2);
+    assert_eq!(char_width('\u{1F600}'), 2);
+    assert_eq!(char_width('\u{0301}'), 0);
+    assert_eq!(char_width('\u{200D}'), 0);
+    assert_eq!(char_width('\u{FE0F}'), 0);
+    assert_eq!(char_width('\t'), 0);
+  }
+
+  #[test]
+  fn skips_escape_sequences() {
+    assert_eq!(display_width(""), 0);
+    assert_eq!(display_width("\x1b[1;38;2;255;0;0mred\x1b[0m"), 3);
+    assert_eq!(
+      display_width("\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07"),
+      4
+    );
+    assert_eq!(display_width("e\u{0301}日本"), 5);
+    // An unterminated sequence swallows the rest of the string
+    assert_eq!(display_width("ab\x1b[31"), 2);
+  }
+
+  #[test]
+  fn truncates_to_columns() {
+    assert_eq!(truncate("abc", 3), None);
+    assert_eq!(truncate("abcd", 3).as_deref(), Some("ab…"));
+    assert_eq!(truncate("abcd", 1).as_deref(), Some("…"));
+    assert_eq!(truncate("abcd", 0).as_deref(), Some(""));
+    assert_eq!(
+      truncate("a\x1b[31mbcdef\x1b[0m", 3).as_deref(),
+      Some("a\x1b[31mb…")
+    );
+    // A wide character that would straddle the cut is dropped whole
+    assert_eq!(truncate("日本語", 4).as_deref(), Some("日…"));
+    assert_eq!(truncate("日本語", 6), None);
+  }
+}
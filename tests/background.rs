@@ -0,0 +1,132 @@
+//! Runs microfetch on a pseudo terminal that answers the OSC 11 background
+//! query, as a terminal emulator would.
+
+use std::{
+  io::{self, Read, Write},
+  os::{
+    fd::{AsRawFd, FromRawFd, OwnedFd},
+    unix::process::CommandExt,
+  },
+  process::{Command, Stdio},
+  ptr,
+  time::{Duration, Instant},
+};
+
+/// The user name, painted yellow by the default palette and a darker yellow by
+/// the one for light backgrounds.
+const DARK: &str = "\x1b[33muser";
+const LIGHT: &str = "\x1b[38;5;136muser";
+
+/// The device attributes of a VT220, which end every answer.
+const ATTRIBUTES: &[u8] = b"\x1b[?62;22c";
+
+/// Opens a pseudo terminal of 80 by 24 cells, returning its master and slave
+/// ends.
+fn open_pty() -> io::Result<(OwnedFd, OwnedFd)> {
+  let mut master = -1;
+  let mut slave = -1;
+  let size = libc::winsize {
+    ws_row:    24,
+    ws_col:    80,
+    ws_xpixel: 0,
+    ws_ypixel: 0,
+  };
+  let result = unsafe {
+    libc::openpty(
+      &raw mut master,
+      &raw mut slave,
+      ptr::null_mut(),
+      ptr::null(),
+      &raw const size,
+    )
+  };
+  if result != 0 {
+    return Err(io::Error::last_os_error());
+  }
+  unsafe { Ok((OwnedFd::from_raw_fd(master), OwnedFd::from_raw_fd(slave))) }
+}
+
+/// Runs `microfetch --mock --background detect` with the pseudo terminal as
+/// its controlling terminal, writing `reply` once it asks for the device
+/// attributes, and returns everything it printed.
+fn run(reply: &[u8]) -> String {
+  let (master, slave) = open_pty().unwrap();
+  let mut command = Command::new(env!("CARGO_BIN_EXE_microfetch"));
+  command
+    .args(["--mock", "--background", "detect"])
+    .env_clear()
+    .env("TERM", "xterm-256color")
+    .env("COLORTERM", "truecolor")
+    .env("HOME", "/nonexistent")
+    .stdin(Stdio::from(slave.try_clone().unwrap()))
+    .stdout(Stdio::from(slave.try_clone().unwrap()))
+    .stderr(Stdio::from(slave));
+  // Start a new session with the pseudo terminal as its controlling terminal,
+  // so that microfetch can open `/dev/tty`
+  unsafe {
+    command.pre_exec(|| {
+      if libc::setsid() < 0
+        || libc::ioctl(io::stdin().as_raw_fd(), libc::TIOCSCTTY, 0) < 0
+      {
+        return Err(io::Error::last_os_error());
+      }
+      Ok(())
+    });
+  }
+  let mut child = command.spawn().unwrap();
+  // Close our copies of the slave, so that reading ends when microfetch exits
+  drop(command);
+
+  let mut master = std::fs::File::from(master);
+  let mut output = Vec::new();
+  let mut answered = false;
+  let deadline = Instant::now() + Duration::from_secs(10);
+  let mut buffer = [0u8; 4096];
+  while Instant::now() < deadline {
+    // Reading fails with EIO once the child has exited and closed the slave
+    let read = match master.read(&mut buffer) {
+      Ok(0) | Err(_) => break,
+      Ok(read) => read,
+    };
+    output.extend_from_slice(&buffer[..read]);
+    if !answered && output.windows(3).any(|w| w == b"\x1b[c") {
+      answered = true;
+      master.write_all(reply).unwrap();
+    }
+  }
+
+  assert!(child.wait().unwrap().success());
+  assert!(answered, "no background query in {output:?}");
+  String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn light_background() {
+  let reply = [b"\x1b]11;rgb:ffff/ffff/f0f0\x1b\\", ATTRIBUTES].concat();
+  let output = run(&reply);
+  assert!(output.contains(LIGHT), "{output:?}");
+  assert!(!output.contains(DARK), "{output:?}");
+}
+
+#[test]
+fn dark_background() {
+  let reply = [b"\x1b]11;rgb:1e1e/1e1e/2e2e\x07", ATTRIBUTES].concat();
+  let output = run(&reply);
+  assert!(output.contains(DARK), "{output:?}");
+  assert!(!output.contains(LIGHT), "{output:?}");
+}
+
+#[test]
+fn no_device_attributes() {
+  // The background still counts once the query times out
+  let output = run(b"\x1b]11;rgb:ffff/ffff/ffff\x07");
+  assert!(output.contains(LIGHT), "{output:?}");
+  assert!(!output.contains(DARK), "{output:?}");
+}
+
+#[test]
+fn no_answer() {
+  let output = run(b"");
+  assert!(output.contains(DARK), "{output:?}");
+  assert!(!output.contains(LIGHT), "{output:?}");
+}
@@ -0,0 +1,109 @@
+//! Renders snapshots and compares the output byte for byte with the files in
+//! `tests/golden`. Run with `MICROFETCH_BLESS=1` to rewrite them after an
+//! intended change to the output.
+
+use std::{
+  path::PathBuf,
+  process::{Command, Output},
+};
+
+fn golden_dir() -> PathBuf {
+  PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// Runs microfetch with `args` in a fixed environment, 100 columns wide
+/// unless `env` says otherwise.
+fn run(args: &[&str], env: &[(&str, &str)]) -> Output {
+  Command::new(env!("CARGO_BIN_EXE_microfetch"))
+    .args(args)
+    .current_dir(golden_dir())
+    .env_clear()
+    .env("TERM", "xterm-256color")
+    .env("COLORTERM", "truecolor")
+    .env("HOME", "/nonexistent")
+    .env("COLUMNS", "100")
+    .envs(env.iter().copied())
+    .output()
+    .unwrap()
+}
+
+/// Runs microfetch like [`run`] and checks its output against `name`.
+fn check(name: &str, args: &[&str], env: &[(&str, &str)]) {
+  let output = run(args, env);
+  assert!(
+    output.status.success(),
+    "{name}: {}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+  assert!(output.stderr.is_empty(), "{name}: unexpected warnings");
+
+  let path = golden_dir().join(name);
+  if std::env::var_os("MICROFETCH_BLESS").is_some() {
+    std::fs::write(&path, &output.stdout).unwrap();
+    return;
+  }
+  let expected = std::fs::read(&path).unwrap();
+  assert!(
+    output.stdout == expected,
+    "{name} differs:\n{}",
+    String::from_utf8_lossy(&output.stdout)
+  );
+}
+
+#[test]
+fn mock() {
+  check("mock.txt", &["--mock", "--color", "never"], &[]);
+  check("mock-color.txt", &["--mock", "--color", "always"], &[]);
+}
+
+#[test]
+fn mock_narrow() {
+  check("mock-narrow.txt", &["--mock", "--color", "never"], &[(
+    "COLUMNS", "48",
+  )]);
+}
+
+#[test]
+fn snapshot() {
+  let load = ["--load", "arch.json"];
+  check(
+    "arch.txt",
+    &[&load[..], &["--color", "always"]].concat(),
+    &[],
+  );
+  check(
+    "arch-above-small.txt",
+    &[&load[..], &[
+      "--color", "never", "--layout", "above", "--small",
+    ]]
+    .concat(),
+    &[],
+  );
+}
+
+#[test]
+fn configured() {
+  let config = golden_dir().join("config");
+  check(
+    "arch-configured.txt",
+    &["--load", "arch.json", "--color", "always"],
+    &[("XDG_CONFIG_HOME", config.to_str().unwrap())],
+  );
+}
+
+#[test]
+fn broken_configuration() {
+  // The file has an unknown key on its second line
+  let config = golden_dir().join("broken");
+  let output = run(&["--mock", "--color", "never"], &[(
+    "XDG_CONFIG_HOME",
+    config.to_str().unwrap(),
+  )]);
+  assert!(output.status.success());
+  assert_eq!(
+    String::from_utf8_lossy(&output.stderr),
+    "microfetch: config:2: unknown key 'colour', ignoring it\n"
+  );
+  let expected = std::fs::read(golden_dir().join("mock.txt")).unwrap();
+  assert!(output.stdout == expected, "the defaults were not used");
+}
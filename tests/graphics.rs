@@ -0,0 +1,98 @@
+//! Escape sequences for image logos, checked byte for byte.
+
+use microfetch_lib::{
+  graphics::{Graphic, Protocol},
+  image::Image,
+};
+
+const RED: [u8; 4] = [255, 0, 0, 255];
+const BLUE: [u8; 4] = [0, 0, 255, 255];
+const CLEAR: [u8; 4] = [0, 0, 0, 0];
+
+fn image(width: u32, height: u32, pixels: &[[u8; 4]]) -> Image {
+  assert_eq!(pixels.len(), width as usize * height as usize);
+  Image {
+    width,
+    height,
+    pixels: pixels.concat(),
+  }
+}
+
+fn kitty(image: &Image) -> String {
+  Graphic::new(image, Protocol::Kitty).sequence
+}
+
+fn sixel(image: &Image) -> String {
+  Graphic::new(image, Protocol::Sixel).sequence
+}
+
+#[test]
+fn kitty_single_chunk() {
+  assert_eq!(
+    kitty(&image(1, 1, &[RED])),
+    "\x1b_Ga=T,f=32,s=1,v=1,C=1,q=2,m=0;/wAA/w==\x1b\\"
+  );
+  assert_eq!(
+    kitty(&image(2, 1, &[RED, CLEAR])),
+    "\x1b_Ga=T,f=32,s=2,v=1,C=1,q=2,m=0;/wAA/wAAAAA=\x1b\\"
+  );
+}
+
+#[test]
+fn kitty_chunks() {
+  // 4096 bytes of pixels take 5464 bytes of base64, split after 4096
+  let sequence = kitty(&image(32, 32, &[BLUE; 32 * 32]));
+  let chunks: Vec<&str> = sequence.split_terminator("\x1b\\").collect();
+  assert_eq!(chunks.len(), 2);
+
+  let first = chunks[0]
+    .strip_prefix("\x1b_Ga=T,f=32,s=32,v=32,C=1,q=2,m=1;")
+    .unwrap();
+  let last = chunks[1].strip_prefix("\x1b_Gm=0;").unwrap();
+  assert_eq!((first.len(), last.len()), (4096, 1368));
+
+  // Every 12 bytes, three pixels, encode the same way
+  let payload = first.to_owned() + last;
+  assert_eq!(payload, "AAD//wAA//8AAP//".repeat(341) + "AAD//w==");
+}
+
+#[test]
+fn sixel_colors_and_bands() {
+  assert_eq!(
+    sixel(&image(2, 2, &[RED, CLEAR, BLUE, RED])),
+    concat!(
+      "\x1bP0;1;0q\"1;1;2;2",
+      "#5;2;0;0;100#180;2;100;0;0",
+      "#5A$#180@A$-",
+      "\x1b\\",
+    )
+  );
+  assert_eq!(
+    sixel(&image(1, 7, &[RED; 7])),
+    "\x1bP0;1;0q\"1;1;1;7#180;2;100;0;0#180~$-#180@$-\x1b\\"
+  );
+}
+
+#[test]
+fn sixel_runs() {
+  assert_eq!(
+    sixel(&image(3, 1, &[RED; 3])),
+    "\x1bP0;1;0q\"1;1;3;1#180;2;100;0;0#180@@@$-\x1b\\"
+  );
+  assert_eq!(
+    sixel(&image(8, 1, &[RED, RED, RED, RED, RED, BLUE, CLEAR, CLEAR])),
+    concat!(
+      "\x1bP0;1;0q\"1;1;8;1#5;2;0;0;100#180;2;100;0;0",
+      "#5!5?@$#180!5@$-",
+      "\x1b\\",
+    )
+  );
+}
+
+#[test]
+fn sixel_transparent_image() {
+  assert_eq!(
+    sixel(&image(2, 1, &[CLEAR, [255, 255, 255, 127]])),
+    "\x1bP0;1;0q\"1;1;2;1-\x1b\\"
+  );
+}